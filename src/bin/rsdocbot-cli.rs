@@ -0,0 +1,178 @@
+use std::{
+    env,
+    io::{self, BufRead, IsTerminal, Write},
+    process,
+};
+
+use rsdocbot::{
+    db::{DocKey, DocumentStore},
+    docs::{strip_tags, Documentation},
+    index::{resolve_path, Resolution, StdIndex},
+    path::DocPath,
+    render::{AnsiRenderer, PlainRenderer, Renderer},
+};
+
+fn main() {
+    pretty_env_logger::init();
+
+    let args: Vec<_> = env::args().skip(1).collect();
+    let input = match args.as_slice() {
+        [command, input] if command == "docs" => input,
+        _ => {
+            eprintln!("Usage: rsdocbot-cli docs <path>");
+            process::exit(2);
+        }
+    };
+    let (name, fragment) = match input.split_once('#') {
+        Some((name, fragment)) => (name, Some(fragment)),
+        None => (input.as_str(), None),
+    };
+    let mut path = match DocPath::try_from(name) {
+        Ok(path) => path,
+        Err(e) => {
+            eprintln!("{}", strip_tags(&e.explain(name)));
+            process::exit(2);
+        }
+    };
+    if let Some(fragment) = fragment {
+        path = path.with_fragment(fragment);
+    }
+    let path = match resolve(path) {
+        Ok(Some(path)) => path,
+        Ok(None) => process::exit(1),
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    };
+
+    let is_terminal = io::stdout().is_terminal();
+    let renderer: &'static dyn Renderer = if is_terminal {
+        &AnsiRenderer
+    } else {
+        &PlainRenderer
+    };
+    let mut cached_docs = DocumentStore::new(renderer);
+    match cached_docs.get_or_fetch(&DocKey::Item(path)) {
+        Ok(Some(doc)) => {
+            let result = if is_terminal {
+                page(doc)
+            } else {
+                print_all(doc)
+            };
+            if let Err(e) = result {
+                eprintln!("{}", e);
+                process::exit(1);
+            }
+        }
+        Ok(None) => {
+            eprintln!("Cannot find that item.");
+            process::exit(1);
+        }
+        Err(e) => {
+            eprintln!("cannot fetch documentation: {}", e);
+            process::exit(1);
+        }
+    }
+}
+
+fn resolve(path: DocPath) -> io::Result<Option<DocPath>> {
+    let index = if path.is_bare() {
+        StdIndex::fetch()
+            .map_err(|e| log::error!("cannot fetch std index: {}", e))
+            .ok()
+    } else {
+        None
+    };
+    let candidates = match resolve_path(index.as_ref(), path) {
+        Resolution::Found(path) => return Ok(Some(path)),
+        Resolution::Ambiguous(candidates) => candidates,
+    };
+
+    eprintln!("Several items are named {}:", candidates[0].item_name());
+    for (i, candidate) in candidates.iter().enumerate() {
+        eprintln!("{:>3}. {}", i + 1, candidate);
+    }
+    if !io::stdin().is_terminal() {
+        return Ok(None);
+    }
+    let choice = read_line("item > ")?
+        .and_then(|input| input.parse::<usize>().ok())
+        .and_then(|choice| candidates.get(choice.checked_sub(1)?));
+    Ok(choice.cloned())
+}
+
+fn print_all(doc: &Documentation) -> io::Result<()> {
+    let mut stdout = io::stdout().lock();
+    for page in &doc.pages {
+        writeln!(stdout, "{}\n", page.text)?;
+    }
+    Ok(())
+}
+
+fn page(doc: &Documentation) -> io::Result<()> {
+    let len = doc.pages.len();
+    let mut index = doc.start.min(len.saturating_sub(1));
+    while let Some(page) = doc.pages.get(index) {
+        print!("\x1b[2J\x1b[H{}\n\n", page.text);
+        let prompt = format!(
+            "[{} / {}] n: next, p: previous, s: sections, l: listings, m: main, <number>: go to page, q: quit > ",
+            index + 1,
+            len
+        );
+        let input = match read_line(&prompt)? {
+            Some(input) => input,
+            None => break,
+        };
+        match input.as_str() {
+            "" | "n" => index = (index + 1).min(len - 1),
+            "p" => index = index.saturating_sub(1),
+            "q" => break,
+            "m" => index = 0,
+            "l" => {
+                if doc.listings.is_empty() {
+                    continue;
+                }
+                for (i, (heading, _)) in doc.listings.iter().enumerate() {
+                    println!("{:>3}. {}", i + 1, heading);
+                }
+                let listing = read_line("listing > ")?
+                    .and_then(|input| input.parse::<usize>().ok())
+                    .and_then(|listing| doc.listings.get(listing.checked_sub(1)?));
+                if let Some((_, page)) = listing {
+                    index = *page;
+                }
+            }
+            "s" => {
+                if doc.sections.is_empty() {
+                    continue;
+                }
+                for (i, (heading, _)) in doc.sections.iter().enumerate() {
+                    println!("{:>3}. {}", i + 1, heading);
+                }
+                let section = read_line("section > ")?
+                    .and_then(|input| input.parse::<usize>().ok())
+                    .and_then(|section| doc.sections.get(section.checked_sub(1)?));
+                if let Some((_, page)) = section {
+                    index = *page;
+                }
+            }
+            input => {
+                if let Ok(page) = input.parse::<usize>() {
+                    index = page.clamp(1, len) - 1;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn read_line(prompt: &str) -> io::Result<Option<String>> {
+    print!("{}", prompt);
+    io::stdout().flush()?;
+    let mut line = String::new();
+    if io::stdin().lock().read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    Ok(Some(line.trim().into()))
+}
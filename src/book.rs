@@ -0,0 +1,472 @@
+use paradocs::{Paragraph, TextPart, TextStyle};
+use regex::Regex;
+use scraper::{ElementRef, Html, Node, Selector};
+use serde_json::Value;
+use telbot_ureq::types::markup::ParseMode;
+use url::Url;
+
+use crate::{
+    docs::{build_pages, fetch_page, linked_title, AutoPaginateWriter, Documentation},
+    render::Renderer,
+};
+
+pub struct Section {
+    pub heading: String,
+    pub level: usize,
+    pub anchor: Option<String>,
+    pub blocks: Vec<Block>,
+}
+
+pub enum Block {
+    Text(Vec<Span>),
+    List(Vec<Vec<Span>>),
+    Code(String),
+    Preformatted(String),
+}
+
+pub enum Span {
+    Text(String),
+    Begin(Style),
+    End,
+}
+
+pub enum Style {
+    Bold,
+    Italic,
+    Code,
+    Link(String),
+}
+
+pub fn parse_sections(html: &str) -> Vec<Section> {
+    let html = Html::parse_document(html);
+    let mut sections = vec![];
+    if let Some(main) = html.select(&Selector::parse("main").unwrap()).next() {
+        parse_blocks(main, &mut sections);
+    }
+    sections
+}
+
+fn parse_blocks(element: ElementRef, sections: &mut Vec<Section>) {
+    for child in element.children().filter_map(ElementRef::wrap) {
+        let block = match child.value().name() {
+            name @ ("h1" | "h2" | "h3" | "h4" | "h5" | "h6") => {
+                sections.push(Section {
+                    heading: child.text().collect::<String>().trim().into(),
+                    level: name[1..].parse().unwrap(),
+                    anchor: child.value().id().map(String::from),
+                    blocks: vec![],
+                });
+                continue;
+            }
+            "p" => {
+                let mut spans = vec![];
+                parse_spans(child, &mut spans);
+                Block::Text(spans)
+            }
+            "ul" | "ol" => Block::List(
+                child
+                    .children()
+                    .filter_map(ElementRef::wrap)
+                    .filter(|item| item.value().name() == "li")
+                    .map(|item| {
+                        let mut spans = vec![];
+                        parse_spans(item, &mut spans);
+                        spans
+                    })
+                    .collect(),
+            ),
+            "pre" => {
+                let mut code = String::new();
+                code_text(child, &mut code);
+                let code = code.trim_end().into();
+                if is_rust_pre(child) {
+                    Block::Code(code)
+                } else {
+                    Block::Preformatted(code)
+                }
+            }
+            "dl" => {
+                for item in child.children().filter_map(ElementRef::wrap) {
+                    match item.value().name() {
+                        "dt" => push_block(sections, definition_term(item)),
+                        "dd" => parse_definition(item, sections),
+                        _ => {}
+                    }
+                }
+                continue;
+            }
+            "div" | "blockquote" | "section" => {
+                parse_blocks(child, sections);
+                continue;
+            }
+            _ => continue,
+        };
+        push_block(sections, block);
+    }
+}
+
+fn push_block(sections: &mut Vec<Section>, block: Block) {
+    if sections.is_empty() {
+        sections.push(Section {
+            heading: String::new(),
+            level: 0,
+            anchor: None,
+            blocks: vec![],
+        });
+    }
+    sections.last_mut().unwrap().blocks.push(block);
+}
+
+fn definition_term(term: ElementRef) -> Block {
+    let mut spans = vec![Span::Begin(Style::Bold)];
+    parse_spans(term, &mut spans);
+    spans.push(Span::End);
+    Block::Text(spans)
+}
+
+fn parse_definition(definition: ElementRef, sections: &mut Vec<Section>) {
+    let has_blocks = definition
+        .children()
+        .filter_map(ElementRef::wrap)
+        .any(|child| {
+            matches!(
+                child.value().name(),
+                "p" | "pre" | "ul" | "ol" | "dl" | "div"
+            )
+        });
+    if has_blocks {
+        parse_blocks(definition, sections);
+    } else {
+        let mut spans = vec![];
+        parse_spans(definition, &mut spans);
+        push_block(sections, Block::Text(spans));
+    }
+}
+
+pub fn find_definition(html: &str, id: &str) -> Option<Vec<Block>> {
+    let html = Html::parse_document(html);
+    let term = html
+        .select(&Selector::parse("dt").unwrap())
+        .find(|term| term.value().id() == Some(id))?;
+    let mut sections = vec![];
+    push_block(&mut sections, definition_term(term));
+    for sibling in term.next_siblings().filter_map(ElementRef::wrap) {
+        if sibling.value().name() != "dd" {
+            break;
+        }
+        parse_definition(sibling, &mut sections);
+    }
+    Some(
+        sections
+            .into_iter()
+            .flat_map(|section| section.blocks)
+            .collect(),
+    )
+}
+
+fn parse_spans(element: ElementRef, spans: &mut Vec<Span>) {
+    for child in element.children() {
+        match child.value() {
+            Node::Text(text) => spans.push(Span::Text(text.to_string())),
+            Node::Element(inner) => {
+                let style = match inner.name() {
+                    "strong" | "b" => Some(Style::Bold),
+                    "em" | "i" => Some(Style::Italic),
+                    "code" => Some(Style::Code),
+                    "a" => inner.attr("href").map(|href| Style::Link(href.into())),
+                    _ => None,
+                };
+                let is_styled = style.is_some();
+                if let Some(style) = style {
+                    spans.push(Span::Begin(style));
+                }
+                parse_spans(ElementRef::wrap(child).unwrap(), spans);
+                if is_styled {
+                    spans.push(Span::End);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn is_rust_pre(pre: ElementRef) -> bool {
+    if pre.value().classes().any(|class| class == "rust") {
+        return true;
+    }
+    let info: Vec<_> = pre
+        .children()
+        .filter_map(ElementRef::wrap)
+        .flat_map(|code| code.value().classes())
+        .filter_map(|class| class.strip_prefix("language-"))
+        .collect();
+    is_rust_code(&info.join(","))
+}
+
+pub fn is_rust_code(info: &str) -> bool {
+    info.split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|attribute| !attribute.is_empty())
+        .all(|attribute| {
+            matches!(
+                attribute,
+                "rust" | "ignore" | "no_run" | "compile_fail" | "should_panic" | "test_harness"
+            ) || attribute.starts_with("edition")
+                || attribute.starts_with('E')
+        })
+}
+
+fn code_text(element: ElementRef, buffer: &mut String) {
+    for child in element.children() {
+        match child.value() {
+            Node::Text(text) => buffer.push_str(text),
+            Node::Element(inner) if !inner.classes().any(|class| class == "boring") => {
+                code_text(ElementRef::wrap(child).unwrap(), buffer);
+            }
+            _ => {}
+        }
+    }
+}
+
+fn spans_to_parts(spans: &[Span]) -> Vec<TextPart<'_>> {
+    spans
+        .iter()
+        .map(|span| match span {
+            Span::Text(text) => TextPart::Text(text),
+            Span::Begin(Style::Bold) => TextPart::BeginStyle(TextStyle::Bold),
+            Span::Begin(Style::Italic) => TextPart::BeginStyle(TextStyle::Italic),
+            Span::Begin(Style::Code) => TextPart::BeginStyle(TextStyle::Monospaced),
+            Span::Begin(Style::Link(href)) => TextPart::BeginStyle(TextStyle::Link(href)),
+            Span::End => TextPart::EndStyle,
+        })
+        .collect()
+}
+
+pub fn write_blocks(
+    writer: &mut AutoPaginateWriter,
+    title: &[TextPart],
+    blocks: &[&Block],
+    base_url: &Url,
+) {
+    writer.write_entries(title, blocks, base_url, |writer, block| match block {
+        Block::Text(spans) => {
+            writer.write_paragraph(&Paragraph::Text(spans_to_parts(spans)), base_url)
+        }
+        Block::List(items) => writer.write_paragraph(
+            &Paragraph::List(items.iter().map(|spans| spans_to_parts(spans)).collect()),
+            base_url,
+        ),
+        Block::Code(code) => writer.write_code_block(&[TextPart::Text(code)]),
+        Block::Preformatted(code) => writer.write_preformatted(code),
+    });
+}
+
+pub fn render_sections(sections: &[Section], url: &Url, renderer: &dyn Renderer) -> Documentation {
+    build_pages(renderer, |writer| {
+        for section in sections {
+            if section.blocks.is_empty() {
+                continue;
+            }
+            let mut section_url = url.clone();
+            section_url.set_fragment(section.anchor.as_deref());
+            let blocks: Vec<_> = section.blocks.iter().collect();
+            write_blocks(
+                writer,
+                &linked_title(&section.heading, &section_url),
+                &blocks,
+                url,
+            );
+        }
+    })
+}
+
+pub fn normalize_error_code(code: &str) -> Option<String> {
+    let digits = code.trim_start_matches(['E', 'e']);
+    if !digits.is_empty() && digits.len() <= 4 && digits.chars().all(|c| c.is_ascii_digit()) {
+        Some(format!("E{:0>4}", digits))
+    } else {
+        None
+    }
+}
+
+pub fn fetch_error_code(
+    code: &str,
+    renderer: &dyn Renderer,
+) -> Result<Option<Documentation>, ureq::Error> {
+    fetch_book_section(
+        &format!("https://doc.rust-lang.org/error_codes/{}.html", code),
+        renderer,
+    )
+}
+
+pub fn fetch_attribute(
+    name: &str,
+    renderer: &dyn Renderer,
+) -> Result<Option<Documentation>, ureq::Error> {
+    match find_link(
+        "https://doc.rust-lang.org/reference/attributes.html",
+        &[name],
+    )? {
+        Some(section) => fetch_book_section(section.as_str(), renderer),
+        None => Ok(None),
+    }
+}
+
+pub fn find_link(index_url: &str, labels: &[&str]) -> Result<Option<Url>, ureq::Error> {
+    let (url, html) = match fetch_page(index_url)? {
+        Some(page) => page,
+        None => return Ok(None),
+    };
+    Ok(labels.iter().find_map(|label| {
+        let link = Regex::new(&format!(
+            r#"<a href="([^"]+)"><code>{}</code></a>"#,
+            regex::escape(&ParseMode::HTML.escape(label))
+        ))
+        .unwrap();
+        link.captures(&html)
+            .and_then(|captures| url.join(&captures[1]).ok())
+    }))
+}
+
+pub fn fetch_book_section(
+    url: &str,
+    renderer: &dyn Renderer,
+) -> Result<Option<Documentation>, ureq::Error> {
+    let anchor = Url::parse(url)
+        .ok()
+        .and_then(|url| url.fragment().map(String::from));
+    let (url, html) = match fetch_page(url)? {
+        Some(page) => page,
+        None => return Ok(None),
+    };
+    let sections = parse_sections(&html);
+    let sections = match sections
+        .iter()
+        .position(|section| anchor.is_some() && section.anchor == anchor)
+    {
+        Some(begin) => {
+            let level = sections[begin].level;
+            let end = sections[begin + 1..]
+                .iter()
+                .position(|section| section.level <= level)
+                .map_or(sections.len(), |end| begin + 1 + end);
+            &sections[begin..end]
+        }
+        None => &sections[..],
+    };
+    if sections.iter().all(|section| section.blocks.is_empty()) {
+        return Ok(None);
+    }
+    Ok(Some(render_sections(sections, &url, renderer)))
+}
+
+pub struct Book {
+    pub title: &'static str,
+    url: &'static str,
+}
+
+pub fn find_book(command: &str) -> Option<&'static Book> {
+    let book = match command {
+        "/book" => &Book {
+            title: "The Rust Programming Language",
+            url: "https://doc.rust-lang.org/book/",
+        },
+        "/reference" => &Book {
+            title: "The Rust Reference",
+            url: "https://doc.rust-lang.org/reference/",
+        },
+        "/nomicon" => &Book {
+            title: "The Rustonomicon",
+            url: "https://doc.rust-lang.org/nomicon/",
+        },
+        "/rbe" => &Book {
+            title: "Rust by Example",
+            url: "https://doc.rust-lang.org/rust-by-example/",
+        },
+        "/cargobook" => &Book {
+            title: "The Cargo Book",
+            url: "https://doc.rust-lang.org/cargo/",
+        },
+        _ => return None,
+    };
+    Some(book)
+}
+
+struct SearchEntry {
+    url: String,
+    breadcrumbs: String,
+    title: String,
+    body: String,
+}
+
+pub struct BookIndex {
+    entries: Vec<SearchEntry>,
+}
+
+impl BookIndex {
+    pub fn fetch(book: &Book) -> Result<Option<Self>, ureq::Error> {
+        let base = Url::parse(book.url).unwrap();
+        let mut script = None;
+        for file in ["searchindex.json", "searchindex.js"] {
+            if let Some((_, text)) = fetch_page(base.join(file).unwrap().as_str())? {
+                script = Some(text);
+                break;
+            }
+        }
+        let json = match script.as_deref().and_then(|script| {
+            let begin = script.find('{')?;
+            let end = script.rfind('}')?;
+            serde_json::from_str::<Value>(&script[begin..=end]).ok()
+        }) {
+            Some(json) => json,
+            None => return Ok(None),
+        };
+
+        let urls = json["doc_urls"].as_array().cloned().unwrap_or_default();
+        let mut entries = vec![];
+        if let Some(docs) = json["index"]["documentStore"]["docs"].as_object() {
+            for doc in docs.values() {
+                let url = doc["id"]
+                    .as_str()
+                    .and_then(|id| id.parse::<usize>().ok())
+                    .and_then(|id| urls.get(id))
+                    .and_then(Value::as_str)
+                    .and_then(|url| base.join(url).ok());
+                if let Some(url) = url {
+                    entries.push(SearchEntry {
+                        url: url.into(),
+                        breadcrumbs: doc["breadcrumbs"].as_str().unwrap_or_default().into(),
+                        title: doc["title"].as_str().unwrap_or_default().to_lowercase(),
+                        body: doc["body"].as_str().unwrap_or_default().to_lowercase(),
+                    });
+                }
+            }
+        }
+        Ok(Some(Self { entries }))
+    }
+
+    pub fn search(&self, query: &str) -> Vec<(String, String)> {
+        let words: Vec<_> = query.split_whitespace().map(str::to_lowercase).collect();
+        let mut hits: Vec<_> = self
+            .entries
+            .iter()
+            .filter_map(|entry| {
+                let mut score = 0;
+                for word in &words {
+                    let in_body = entry.body.matches(word.as_str()).count().min(10);
+                    if entry.title.contains(word.as_str()) {
+                        score += 20;
+                    } else if in_body == 0 {
+                        return None;
+                    }
+                    score += in_body;
+                }
+                Some((score, entry))
+            })
+            .collect();
+        hits.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+        hits.into_iter()
+            .take(8)
+            .map(|(_, entry)| (entry.breadcrumbs.clone(), entry.url.clone()))
+            .collect()
+    }
+}
@@ -0,0 +1,59 @@
+use crate::{
+    book::{fetch_book_section, find_definition, find_link, render_sections, Section},
+    docs::{fetch_page, Documentation},
+    render::Renderer,
+};
+
+const CARGO_BOOK: &str = "https://doc.rust-lang.org/cargo/";
+
+pub fn fetch_cargo_command(
+    command: &str,
+    flag: Option<&str>,
+    renderer: &dyn Renderer,
+) -> Result<Option<Documentation>, ureq::Error> {
+    let url = format!("{}commands/cargo-{}.html", CARGO_BOOK, command);
+    let flag = match flag {
+        Some(flag) => flag.split('=').next().unwrap_or(flag),
+        None => return fetch_book_section(&url, renderer),
+    };
+    let (url, html) = match fetch_page(&url)? {
+        Some(page) => page,
+        None => return Ok(None),
+    };
+    let id = format!("option-cargo-{}-{}", command, flag);
+    let blocks = match find_definition(&html, &id) {
+        Some(blocks) => blocks,
+        None => return Ok(None),
+    };
+    let section = Section {
+        heading: format!("cargo {} {}", command, flag),
+        level: 0,
+        anchor: Some(id),
+        blocks,
+    };
+    Ok(Some(render_sections(&[section], &url, renderer)))
+}
+
+pub fn fetch_manifest_key(
+    key: &str,
+    renderer: &dyn Renderer,
+) -> Result<Option<Documentation>, ureq::Error> {
+    let name = key.trim_start_matches('[').trim_end_matches(']');
+    if let Some(profile) = name.strip_prefix("profile.") {
+        let profile = profile.split('.').next().unwrap_or(profile);
+        return fetch_book_section(
+            &format!("{}reference/profiles.html#{}", CARGO_BOOK, profile),
+            renderer,
+        );
+    }
+    let table = format!("[{}]", name);
+    let manifest = format!("{}reference/manifest.html", CARGO_BOOK);
+    match find_link(&manifest, &[&table, name])? {
+        Some(section) => fetch_book_section(section.as_str(), renderer),
+        None => Ok(None),
+    }
+}
+
+pub fn is_subcommand(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+}
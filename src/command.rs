@@ -40,7 +40,6 @@ impl<'a> Command<'a> {
         }
     }
 
-    #[allow(dead_code)]
     pub fn args(&self) -> Args<'a> {
         Args { inner: self.rest }
     }
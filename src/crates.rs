@@ -0,0 +1,635 @@
+use paradocs::{Paragraph, TextPart, TextStyle};
+use serde_json::Value;
+use telbot_ureq::types::markup::{InlineKeyboardButtonKind, InlineKeyboardRow};
+use url::Url;
+
+use crate::{
+    db::DocKey,
+    docs::{
+        add_additional_autopage, add_additional_pager, build_pages, fetch_html,
+        render_documentation, AutoPaginateWriter, Documentation,
+    },
+    path::DocPath,
+    render::Renderer,
+};
+
+const USER_AGENT: &str = "rsdocbot (https://github.com/kiwiyou/rsdocbot)";
+
+pub struct CrateInfo {
+    pub name: String,
+    pub version: String,
+    pub description: Option<String>,
+    pub license: Option<String>,
+    pub repository: Option<String>,
+    pub homepage: Option<String>,
+    pub downloads: u64,
+    pub recent_downloads: u64,
+    pub rust_version: Option<String>,
+    pub features: Vec<(String, Vec<String>)>,
+}
+
+pub struct Dependency {
+    pub name: String,
+    pub req: String,
+    pub kind: String,
+    pub optional: bool,
+    pub features: Vec<String>,
+    pub target: Option<String>,
+}
+
+pub struct Release {
+    pub num: String,
+    pub created_at: String,
+    pub yanked: bool,
+    pub rust_version: Option<String>,
+}
+
+pub fn crates_io(path: &str) -> Result<Option<Value>, ureq::Error> {
+    let url = format!("https://crates.io/api/v1/{}", path);
+    match ureq::get(&url).set("User-Agent", USER_AGENT).call() {
+        Ok(response) => Ok(serde_json::from_str(&response.into_string()?).ok()),
+        Err(ureq::Error::Status(404, _)) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+pub fn split_version(arg: &str) -> (&str, Option<&str>) {
+    match arg.split_once('@') {
+        Some((name, version)) => (name, Some(version)),
+        None => (arg, None),
+    }
+}
+
+pub fn fetch_crate(name: &str, version: Option<&str>) -> Result<Option<CrateInfo>, ureq::Error> {
+    let json = match crates_io(&format!("crates/{}", name))? {
+        Some(json) => json,
+        None => return Ok(None),
+    };
+    let krate = &json["crate"];
+    let version = version
+        .or_else(|| krate["max_stable_version"].as_str())
+        .or_else(|| krate["max_version"].as_str())
+        .unwrap_or_default()
+        .to_string();
+    let release = match json["versions"].as_array().and_then(|versions| {
+        versions
+            .iter()
+            .find(|release| release["num"] == version.as_str())
+    }) {
+        Some(release) => release,
+        None => return Ok(None),
+    };
+    let mut features: Vec<_> = release["features"]
+        .as_object()
+        .map(|features| {
+            features
+                .iter()
+                .map(|(name, enables)| (name.clone(), string_array(enables)))
+                .collect()
+        })
+        .unwrap_or_default();
+    features.sort_by(|(a, _), (b, _)| (a != "default", a).cmp(&(b != "default", b)));
+
+    Ok(Some(CrateInfo {
+        name: string(&krate["name"]).unwrap_or_else(|| name.into()),
+        version,
+        description: string(&krate["description"]),
+        license: string(&release["license"]),
+        repository: string(&krate["repository"]),
+        homepage: string(&krate["homepage"]),
+        downloads: krate["downloads"].as_u64().unwrap_or_default(),
+        recent_downloads: krate["recent_downloads"].as_u64().unwrap_or_default(),
+        rust_version: string(&release["rust_version"]),
+        features,
+    }))
+}
+
+pub fn fetch_crate_overview(
+    name: &str,
+    renderer: &dyn Renderer,
+) -> Result<Option<Documentation>, ureq::Error> {
+    let info = match fetch_crate(name, None)? {
+        Some(info) => info,
+        None => return Ok(None),
+    };
+    let root = DocPath::crate_root(&info.name);
+    let overview = fetch_html(&root)?.and_then(|(url, html)| {
+        render_documentation(&html, &url, renderer, |writer, _| {
+            write_overview(writer, &info, &url)
+        })
+    });
+    Ok(Some(overview.unwrap_or_else(|| {
+        let url = Url::parse(&crate_url(&info.name)).unwrap();
+        build_pages(renderer, |writer| write_overview(writer, &info, &url))
+    })))
+}
+
+fn write_overview(writer: &mut AutoPaginateWriter, info: &CrateInfo, base_url: &Url) {
+    let crate_url = crate_url(&info.name);
+    let title = [
+        TextPart::BeginStyle(TextStyle::Bold),
+        TextPart::BeginStyle(TextStyle::Link(&crate_url)),
+        TextPart::Text(&info.name),
+        TextPart::EndStyle,
+        TextPart::EndStyle,
+        TextPart::Text(" "),
+        TextPart::Text(&info.version),
+    ];
+    writer.write_section(&title, base_url, |writer| {
+        if let Some(description) = &info.description {
+            writer.write_str(description.trim());
+            writer.line_break();
+            writer.line_break();
+        }
+
+        if let Some(license) = &info.license {
+            writer.write_str("License: ");
+            writer.write_str(license);
+            writer.line_break();
+        }
+        if let Some(rust_version) = &info.rust_version {
+            writer.write_str("MSRV: ");
+            writer.write_str(rust_version);
+            writer.line_break();
+        }
+        writer.write_str(&format!(
+            "Downloads: {} ({} recent)",
+            format_count(info.downloads),
+            format_count(info.recent_downloads)
+        ));
+        for (label, link) in [
+            ("Repository", &info.repository),
+            ("Homepage", &info.homepage),
+        ] {
+            if let Some(link) = link {
+                writer.line_break();
+                writer.write_str(label);
+                writer.write_str(": ");
+                writer.apply_style(&TextStyle::Link(link), base_url);
+                writer.write_str(link);
+                writer.remove_style();
+            }
+        }
+
+        if !info.features.is_empty() {
+            writer.line_break();
+            writer.line_break();
+            writer.write_str("Features: ");
+            for (i, (feature, _)) in info.features.iter().enumerate() {
+                if i > 0 {
+                    writer.write_str(", ");
+                }
+                writer.apply_style(&TextStyle::Monospaced, base_url);
+                writer.write_str(feature);
+                writer.remove_style();
+            }
+        }
+    });
+}
+
+pub fn fetch_dependencies(name: &str, version: &str) -> Result<Vec<Dependency>, ureq::Error> {
+    let json = crates_io(&format!("crates/{}/{}/dependencies", name, version))?;
+    let dependencies = json
+        .as_ref()
+        .and_then(|json| json["dependencies"].as_array())
+        .map(|dependencies| {
+            dependencies
+                .iter()
+                .map(|dependency| Dependency {
+                    name: string(&dependency["crate_id"]).unwrap_or_default(),
+                    req: string(&dependency["req"]).unwrap_or_default(),
+                    kind: string(&dependency["kind"]).unwrap_or_else(|| "normal".into()),
+                    optional: dependency["optional"].as_bool().unwrap_or_default(),
+                    features: string_array(&dependency["features"]),
+                    target: string(&dependency["target"]),
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    Ok(dependencies)
+}
+
+struct Feature {
+    name: String,
+    is_default: bool,
+    features: Vec<String>,
+    dependencies: Vec<String>,
+}
+
+pub fn fetch_features(
+    name: &str,
+    version: Option<&str>,
+    renderer: &dyn Renderer,
+) -> Result<Option<Documentation>, ureq::Error> {
+    let info = match fetch_crate(name, version)? {
+        Some(info) => info,
+        None => return Ok(None),
+    };
+    let dependencies = fetch_dependencies(&info.name, &info.version)?;
+
+    let defaults = info
+        .features
+        .iter()
+        .find(|(name, _)| name == "default")
+        .map(|(_, enables)| enables.as_slice())
+        .unwrap_or_default();
+    let mut features: Vec<_> = info
+        .features
+        .iter()
+        .map(|(name, enables)| {
+            let mut feature = Feature {
+                name: name.clone(),
+                is_default: defaults.contains(name),
+                features: vec![],
+                dependencies: vec![],
+            };
+            for enable in enables {
+                if let Some(dependency) = enable.strip_prefix("dep:") {
+                    feature.dependencies.push(dependency.into());
+                } else if enable.contains('/') {
+                    feature.dependencies.push(enable.replace("?/", "/"));
+                } else if info.features.iter().any(|(name, _)| name == enable) {
+                    feature.features.push(enable.clone());
+                } else {
+                    feature.dependencies.push(enable.clone());
+                }
+            }
+            feature
+        })
+        .collect();
+    for dependency in dependencies.iter().filter(|dependency| dependency.optional) {
+        let is_explicit = info.features.iter().any(|(name, enables)| {
+            name == &dependency.name || enables.contains(&format!("dep:{}", dependency.name))
+        });
+        if !is_explicit {
+            features.push(Feature {
+                name: dependency.name.clone(),
+                is_default: defaults.contains(&dependency.name),
+                features: vec![],
+                dependencies: vec![dependency.name.clone()],
+            });
+        }
+    }
+
+    let url = format!(
+        "https://docs.rs/crate/{}/{}/features",
+        info.name, info.version
+    );
+    let title_text = format!("Features of {} {}", info.name, info.version);
+    let title = [
+        TextPart::BeginStyle(TextStyle::Bold),
+        TextPart::BeginStyle(TextStyle::Link(&url)),
+        TextPart::Text(&title_text),
+        TextPart::EndStyle,
+        TextPart::EndStyle,
+    ];
+    let paragraphs: Vec<_> = if features.is_empty() {
+        vec![Paragraph::Text(vec![TextPart::Text(
+            "This crate has no features.",
+        )])]
+    } else {
+        features.iter().map(feature_paragraph).collect()
+    };
+    let url = Url::parse(&url).unwrap();
+    Ok(Some(build_pages(renderer, |writer| {
+        writer.write_paragraphs(&title, &paragraphs, &url)
+    })))
+}
+
+fn feature_paragraph(feature: &Feature) -> Paragraph<'_> {
+    let mut parts = vec![
+        TextPart::BeginStyle(TextStyle::Bold),
+        TextPart::Text(&feature.name),
+        TextPart::EndStyle,
+    ];
+    if feature.is_default {
+        parts.push(TextPart::Text(" (default)"));
+    }
+    let mut separator = " enables ";
+    for (label, enables) in [
+        ("features", &feature.features),
+        ("dependencies", &feature.dependencies),
+    ] {
+        if enables.is_empty() {
+            continue;
+        }
+        parts.push(TextPart::Text(separator));
+        separator = "; ";
+        parts.push(TextPart::Text(label));
+        parts.push(TextPart::Text(" "));
+        for (i, enable) in enables.iter().enumerate() {
+            if i > 0 {
+                parts.push(TextPart::Text(", "));
+            }
+            parts.push(TextPart::BeginStyle(TextStyle::Monospaced));
+            parts.push(TextPart::Text(enable));
+            parts.push(TextPart::EndStyle);
+        }
+    }
+    Paragraph::Text(parts)
+}
+
+pub fn fetch_reverse_dependency_count(name: &str) -> Result<u64, ureq::Error> {
+    let json = crates_io(&format!("crates/{}/reverse_dependencies?per_page=1", name))?;
+    Ok(json
+        .and_then(|json| json["meta"]["total"].as_u64())
+        .unwrap_or_default())
+}
+
+pub fn fetch_dependency_view(
+    name: &str,
+    version: Option<&str>,
+    renderer: &dyn Renderer,
+) -> Result<Option<Documentation>, ureq::Error> {
+    let info = match fetch_crate(name, version)? {
+        Some(info) => info,
+        None => return Ok(None),
+    };
+    let dependencies = fetch_dependencies(&info.name, &info.version)?;
+    let dependents = fetch_reverse_dependency_count(&info.name)?;
+
+    let url = format!("{}/{}/dependencies", crate_url(&info.name), info.version);
+    let dependents_text = format!("Used by {} crates.", format_count(dependents));
+    let sections: Vec<_> = [
+        ("normal", "Dependencies"),
+        ("dev", "Dev dependencies"),
+        ("build", "Build dependencies"),
+    ]
+    .iter()
+    .map(|(kind, label)| {
+        let title = format!("{} of {} {}", label, info.name, info.version);
+        let dependencies: Vec<_> = dependencies
+            .iter()
+            .filter(|dependency| dependency.kind == *kind)
+            .collect();
+        (title, dependencies)
+    })
+    .filter(|(_, dependencies)| !dependencies.is_empty())
+    .collect();
+
+    let base_url = Url::parse(&url).unwrap();
+    let mut doc = build_pages(renderer, |writer| {
+        if sections.is_empty() {
+            let title = format!("{} {}", info.name, info.version);
+            let title = [
+                TextPart::BeginStyle(TextStyle::Bold),
+                TextPart::BeginStyle(TextStyle::Link(&url)),
+                TextPart::Text(&title),
+                TextPart::EndStyle,
+                TextPart::EndStyle,
+            ];
+            let paragraphs = [
+                Paragraph::Text(vec![TextPart::Text("This crate has no dependencies.")]),
+                Paragraph::Text(vec![TextPart::Text(&dependents_text)]),
+            ];
+            writer.write_paragraphs(&title, &paragraphs, &base_url);
+        }
+        for (i, (title, dependencies)) in sections.iter().enumerate() {
+            let title = [
+                TextPart::BeginStyle(TextStyle::Bold),
+                TextPart::BeginStyle(TextStyle::Link(&url)),
+                TextPart::Text(title),
+                TextPart::EndStyle,
+                TextPart::EndStyle,
+            ];
+            let mut paragraphs = vec![];
+            if i == 0 {
+                paragraphs.push(Paragraph::Text(vec![TextPart::Text(&dependents_text)]));
+            }
+            paragraphs.extend(dependencies.iter().map(|dependency| {
+                let gates: Vec<_> = info
+                    .features
+                    .iter()
+                    .filter(|(_, enables)| {
+                        enables
+                            .iter()
+                            .any(|enable| enables_dependency(enable, &dependency.name))
+                    })
+                    .map(|(feature, _)| feature.as_str())
+                    .collect();
+                dependency_paragraph(dependency, gates)
+            }));
+            writer.write_paragraphs(&title, &paragraphs, &base_url);
+        }
+    });
+
+    let mut additionals = vec![];
+    let mut row: Option<InlineKeyboardRow> = None;
+    for (i, dependency) in dependencies.iter().enumerate() {
+        let kind = InlineKeyboardButtonKind::Callback {
+            callback_data: format!("k{}", i),
+        };
+        row = Some(match row {
+            Some(row) => row.emplace(&dependency.name, kind),
+            None => InlineKeyboardRow::new_emplace(&dependency.name, kind),
+        });
+        if i % 3 == 2 {
+            add_additional_autopage(&mut additionals, row.take().unwrap());
+        }
+    }
+    if let Some(row) = row {
+        add_additional_autopage(&mut additionals, row);
+    }
+    add_additional_pager(&mut additionals);
+    for page in &mut doc.pages {
+        page.additionals = additionals.clone();
+    }
+    doc.links = dependencies
+        .iter()
+        .map(|dependency| DocKey::Crate(dependency.name.clone()))
+        .collect();
+    Ok(Some(doc))
+}
+
+fn enables_dependency(enable: &str, dependency: &str) -> bool {
+    let enable = enable.strip_prefix("dep:").unwrap_or(enable);
+    let enable = enable.split('/').next().unwrap_or_default();
+    enable.trim_end_matches('?') == dependency
+}
+
+fn dependency_paragraph<'a>(dependency: &'a Dependency, gates: Vec<&'a str>) -> Paragraph<'a> {
+    let mut parts = vec![
+        TextPart::BeginStyle(TextStyle::Bold),
+        TextPart::Text(&dependency.name),
+        TextPart::EndStyle,
+        TextPart::Text(" "),
+        TextPart::BeginStyle(TextStyle::Monospaced),
+        TextPart::Text(&dependency.req),
+        TextPart::EndStyle,
+    ];
+    if dependency.optional {
+        parts.push(TextPart::Text(" (optional"));
+        for (i, gate) in gates.into_iter().enumerate() {
+            parts.push(TextPart::Text(if i > 0 { ", " } else { ", enabled by " }));
+            parts.push(TextPart::BeginStyle(TextStyle::Monospaced));
+            parts.push(TextPart::Text(gate));
+            parts.push(TextPart::EndStyle);
+        }
+        parts.push(TextPart::Text(")"));
+    }
+    if let Some(target) = &dependency.target {
+        parts.push(TextPart::Text(" on "));
+        parts.push(TextPart::BeginStyle(TextStyle::Monospaced));
+        parts.push(TextPart::Text(target));
+        parts.push(TextPart::EndStyle);
+    }
+    if !dependency.features.is_empty() {
+        parts.push(TextPart::Text(" with features "));
+        for (i, feature) in dependency.features.iter().enumerate() {
+            if i > 0 {
+                parts.push(TextPart::Text(", "));
+            }
+            parts.push(TextPart::BeginStyle(TextStyle::Monospaced));
+            parts.push(TextPart::Text(feature));
+            parts.push(TextPart::EndStyle);
+        }
+    }
+    Paragraph::Text(parts)
+}
+
+pub fn fetch_releases(name: &str) -> Result<Option<(String, Vec<Release>)>, ureq::Error> {
+    let json = match crates_io(&format!("crates/{}", name))? {
+        Some(json) => json,
+        None => return Ok(None),
+    };
+    let name = string(&json["crate"]["name"]).unwrap_or_else(|| name.into());
+    let releases = json["versions"]
+        .as_array()
+        .map(|versions| {
+            versions
+                .iter()
+                .map(|release| Release {
+                    num: string(&release["num"]).unwrap_or_default(),
+                    created_at: release["created_at"]
+                        .as_str()
+                        .unwrap_or_default()
+                        .chars()
+                        .take(10)
+                        .collect(),
+                    yanked: release["yanked"].as_bool().unwrap_or_default(),
+                    rust_version: string(&release["rust_version"]),
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    Ok(Some((name, releases)))
+}
+
+const RELEASES_PER_PAGE: usize = 10;
+
+pub fn fetch_version_history(
+    name: &str,
+    renderer: &dyn Renderer,
+) -> Result<Option<Documentation>, ureq::Error> {
+    let (name, releases) = match fetch_releases(name)? {
+        Some(releases) => releases,
+        None => return Ok(None),
+    };
+    if releases.is_empty() {
+        return Ok(None);
+    }
+
+    let url = format!("{}/versions", crate_url(&name));
+    let title_text = format!("Versions of {}", name);
+    let title = [
+        TextPart::BeginStyle(TextStyle::Bold),
+        TextPart::BeginStyle(TextStyle::Link(&url)),
+        TextPart::Text(&title_text),
+        TextPart::EndStyle,
+        TextPart::EndStyle,
+    ];
+    let base_url = Url::parse(&url).unwrap();
+    let mut placements = vec![];
+    let mut doc = build_pages(renderer, |writer| {
+        for chunk in releases.chunks(RELEASES_PER_PAGE) {
+            let paragraphs: Vec<_> = chunk.iter().map(release_paragraph).collect();
+            placements.extend(writer.write_placed_paragraphs(&title, &paragraphs, &base_url));
+        }
+    });
+
+    for (index, page) in doc.pages.iter_mut().enumerate() {
+        let written: Vec<_> = releases
+            .iter()
+            .enumerate()
+            .zip(&placements)
+            .filter(|(_, placement)| **placement == index)
+            .map(|(release, _)| release)
+            .collect();
+        let rows = written
+            .chunks(3)
+            .map(|releases| {
+                let mut buttons = releases.iter().map(|(i, release)| {
+                    (
+                        release.num.clone(),
+                        InlineKeyboardButtonKind::Callback {
+                            callback_data: format!("k{}", i),
+                        },
+                    )
+                });
+                let (text, kind) = buttons.next().unwrap();
+                buttons.fold(
+                    InlineKeyboardRow::new_emplace(text, kind),
+                    |row, (text, kind)| row.emplace(text, kind),
+                )
+            })
+            .collect();
+        page.additionals = vec![rows];
+    }
+    doc.links = releases
+        .iter()
+        .map(|release| DocKey::Item(DocPath::crate_root(&name).with_version(&release.num)))
+        .collect();
+    Ok(Some(doc))
+}
+
+fn release_paragraph(release: &Release) -> Paragraph<'_> {
+    let mut parts = vec![TextPart::BeginStyle(TextStyle::Bold)];
+    if release.yanked {
+        parts.push(TextPart::BeginStyle(TextStyle::Strikethrough));
+        parts.push(TextPart::Text(&release.num));
+        parts.push(TextPart::EndStyle);
+    } else {
+        parts.push(TextPart::Text(&release.num));
+    }
+    parts.push(TextPart::EndStyle);
+    parts.push(TextPart::Text(" "));
+    parts.push(TextPart::Text(&release.created_at));
+    if let Some(rust_version) = &release.rust_version {
+        parts.push(TextPart::Text(" · MSRV "));
+        parts.push(TextPart::Text(rust_version));
+    }
+    if release.yanked {
+        parts.push(TextPart::Text(" · yanked"));
+    }
+    Paragraph::Text(parts)
+}
+
+pub fn crate_url(name: &str) -> String {
+    format!("https://crates.io/crates/{}", name)
+}
+
+pub fn format_count(count: u64) -> String {
+    let digits = count.to_string();
+    let mut formatted = String::new();
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i) % 3 == 0 {
+            formatted.push(',');
+        }
+        formatted.push(digit);
+    }
+    formatted
+}
+
+pub fn string(value: &Value) -> Option<String> {
+    value
+        .as_str()
+        .filter(|value| !value.is_empty())
+        .map(String::from)
+}
+
+pub fn string_array(value: &Value) -> Vec<String> {
+    value
+        .as_array()
+        .map(|values| values.iter().filter_map(string).collect())
+        .unwrap_or_default()
+}
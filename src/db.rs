@@ -1,24 +1,102 @@
 use std::collections::HashMap;
 
-use crate::{docs::Documentation, path::DocPath};
+use crate::{
+    book::{fetch_attribute, fetch_book_section, fetch_error_code},
+    cargo::{fetch_cargo_command, fetch_manifest_key},
+    crates::{fetch_crate_overview, fetch_dependency_view, fetch_features, fetch_version_history},
+    diff::fetch_diff,
+    docs::{fetch_documentation, Documentation},
+    path::DocPath,
+    render::{renderer, Renderer},
+    source::fetch_source,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum DocKey {
+    Item(DocPath),
+    Crate(String),
+    Features(String, Option<String>),
+    Dependencies(String, Option<String>),
+    Versions(String),
+    Diff(DocPath, String, String),
+    Error(String),
+    Lint(String),
+    Book(String),
+    Attribute(String),
+    Cargo(String, Option<String>),
+    Manifest(String),
+    Source(DocPath),
+    FullExamples(DocPath),
+}
+
+impl DocKey {
+    fn fetch(&self, renderer: &dyn Renderer) -> Result<Option<Documentation>, ureq::Error> {
+        match self {
+            DocKey::Item(path) => fetch_documentation(path, false, renderer),
+            DocKey::FullExamples(path) => fetch_documentation(path, true, renderer),
+            DocKey::Crate(name) => fetch_crate_overview(name, renderer),
+            DocKey::Features(name, version) => fetch_features(name, version.as_deref(), renderer),
+            DocKey::Dependencies(name, version) => {
+                fetch_dependency_view(name, version.as_deref(), renderer)
+            }
+            DocKey::Versions(name) => fetch_version_history(name, renderer),
+            DocKey::Diff(path, old, new) => fetch_diff(path, old, new, renderer),
+            DocKey::Error(code) => fetch_error_code(code, renderer),
+            // Lint pages are rendered from the lint index when they are searched.
+            DocKey::Lint(_) => Ok(None),
+            DocKey::Book(url) => fetch_book_section(url, renderer),
+            DocKey::Attribute(name) => fetch_attribute(name, renderer),
+            DocKey::Cargo(command, flag) => fetch_cargo_command(command, flag.as_deref(), renderer),
+            DocKey::Manifest(key) => fetch_manifest_key(key, renderer),
+            DocKey::Source(path) => fetch_source(path, renderer),
+        }
+    }
+}
 
-#[derive(Default)]
 pub struct DocumentStore {
-    finder: HashMap<DocPath, Documentation>,
+    renderer: &'static dyn Renderer,
+    finder: HashMap<DocKey, Documentation>,
+}
+
+impl Default for DocumentStore {
+    fn default() -> Self {
+        Self::new(renderer())
+    }
 }
 
 impl DocumentStore {
-    pub fn get(&self, path: &DocPath) -> Option<&Documentation> {
-        self.finder.get(path)
+    pub fn new(renderer: &'static dyn Renderer) -> Self {
+        Self {
+            renderer,
+            finder: HashMap::new(),
+        }
+    }
+
+    pub fn renderer(&self) -> &'static dyn Renderer {
+        self.renderer
+    }
+
+    pub fn get(&self, key: &DocKey) -> Option<&Documentation> {
+        self.finder.get(key)
     }
 
-    pub fn insert(&mut self, path: DocPath, doc: Documentation) {
-        self.finder.insert(path, doc);
+    pub fn insert(&mut self, key: DocKey, doc: Documentation) {
+        self.finder.insert(key, doc);
+    }
+
+    pub fn get_or_fetch(&mut self, key: &DocKey) -> Result<Option<&Documentation>, ureq::Error> {
+        if !self.finder.contains_key(key) {
+            match key.fetch(self.renderer)? {
+                Some(doc) => self.insert(key.clone(), doc),
+                None => return Ok(None),
+            }
+        }
+        Ok(self.finder.get(key))
     }
 }
 
 pub struct Session {
-    pub path: DocPath,
+    pub key: DocKey,
     pub page: usize,
 }
 
@@ -28,11 +106,26 @@ pub struct SessionStore {
 }
 
 impl SessionStore {
-    pub fn get(&self, chat_id: i64, message_id: i64) -> Option<&Session> {
-        self.finder.get(&(chat_id, message_id))
+    pub fn get_mut(&mut self, chat_id: i64, message_id: i64) -> Option<&mut Session> {
+        self.finder.get_mut(&(chat_id, message_id))
     }
 
     pub fn insert(&mut self, chat_id: i64, message_id: i64, session: Session) {
         self.finder.insert((chat_id, message_id), session);
     }
 }
+
+#[derive(Default)]
+pub struct ChoiceStore {
+    finder: HashMap<(i64, i64), Vec<DocKey>>,
+}
+
+impl ChoiceStore {
+    pub fn get(&self, chat_id: i64, message_id: i64) -> Option<&Vec<DocKey>> {
+        self.finder.get(&(chat_id, message_id))
+    }
+
+    pub fn insert(&mut self, chat_id: i64, message_id: i64, choices: Vec<DocKey>) {
+        self.finder.insert((chat_id, message_id), choices);
+    }
+}
@@ -0,0 +1,116 @@
+use crate::{
+    docs::{build_pages, fetch_html, linked_title, outline, Documentation},
+    path::DocPath,
+    render::Renderer,
+};
+use paradocs::{Paragraph, TextPart, TextStyle};
+
+pub fn fetch_diff(
+    path: &DocPath,
+    old_version: &str,
+    new_version: &str,
+    renderer: &dyn Renderer,
+) -> Result<Option<Documentation>, ureq::Error> {
+    let (_, old_html) = match fetch_html(&path.clone().with_version(old_version))? {
+        Some(page) => page,
+        None => return Ok(None),
+    };
+    let (url, new_html) = match fetch_html(&path.clone().with_version(new_version))? {
+        Some(page) => page,
+        None => return Ok(None),
+    };
+    let old = outline(&old_html);
+    let new = outline(&new_html);
+
+    let mut added = vec![];
+    let mut changed = vec![];
+    for (id, signature) in &new.members {
+        match old.members.iter().find(|(old_id, _)| old_id == id) {
+            None => added.push(signature),
+            Some((_, old_signature)) if old_signature != signature => {
+                changed.push((old_signature, signature))
+            }
+            _ => {}
+        }
+    }
+    let removed: Vec<_> = old
+        .members
+        .iter()
+        .filter(|(id, _)| !new.members.iter().any(|(new_id, _)| new_id == id))
+        .map(|(_, signature)| signature)
+        .collect();
+
+    let heading = format!("{} {} → {}", path, old_version, new_version);
+    let sections = [
+        (
+            "Declaration",
+            declaration_diff(&old.declaration, &new.declaration),
+        ),
+        (
+            "Added",
+            added.iter().map(|new| added_paragraph(new)).collect(),
+        ),
+        (
+            "Removed",
+            removed.iter().map(|old| removed_paragraph(old)).collect(),
+        ),
+        (
+            "Changed",
+            changed
+                .iter()
+                .flat_map(|(old, new)| [removed_paragraph(old), added_paragraph(new)])
+                .collect(),
+        ),
+    ];
+    let titles: Vec<_> = sections
+        .iter()
+        .map(|(label, _)| format!("{}: {}", heading, label))
+        .collect();
+
+    Ok(Some(build_pages(renderer, |writer| {
+        let mut is_empty = true;
+        for ((_, paragraphs), title) in sections.iter().zip(&titles) {
+            if paragraphs.is_empty() {
+                continue;
+            }
+            is_empty = false;
+            writer.write_paragraphs(&linked_title(title, &url), paragraphs, &url);
+        }
+        if is_empty {
+            writer.write_paragraphs(
+                &linked_title(&heading, &url),
+                &[Paragraph::Text(vec![TextPart::Text(
+                    "No changes between these versions.",
+                )])],
+                &url,
+            );
+        }
+    })))
+}
+
+fn declaration_diff<'a>(old: &'a Option<String>, new: &'a Option<String>) -> Vec<Paragraph<'a>> {
+    match (old, new) {
+        (Some(old), Some(new)) if old != new => {
+            vec![removed_paragraph(old), added_paragraph(new)]
+        }
+        _ => vec![],
+    }
+}
+
+fn added_paragraph(signature: &str) -> Paragraph<'_> {
+    Paragraph::Text(vec![
+        TextPart::Text("+ "),
+        TextPart::BeginStyle(TextStyle::Bold),
+        TextPart::Text(signature),
+        TextPart::EndStyle,
+    ])
+}
+
+fn removed_paragraph(signature: &str) -> Paragraph<'_> {
+    Paragraph::Text(vec![
+        TextPart::Text("− "),
+        TextPart::BeginStyle(TextStyle::Strikethrough),
+        TextPart::Text(signature),
+        TextPart::EndStyle,
+    ])
+}
@@ -1,20 +1,38 @@
+use std::{collections::HashMap, env};
+
 use paradocs::{parse_document, Document, Html, ItemRow, Paragraph, TextPart, TextStyle};
 use regex::Regex;
+use scraper::Selector;
 use telbot_ureq::types::markup::{
-    InlineKeyboardButtonKind, InlineKeyboardMarkup, InlineKeyboardRow, ParseMode,
+    InlineKeyboardButtonKind, InlineKeyboardMarkup, InlineKeyboardRow,
 };
 use url::Url;
 
-use crate::path::DocPath;
+use crate::{
+    db::DocKey,
+    path::DocPath,
+    render::{Block, Markup, Renderer},
+};
 
 #[derive(Clone)]
 pub struct Page {
     pub text: String,
     pub page_keyboard: Option<InlineKeyboardRow>,
     pub additionals: Vec<Vec<InlineKeyboardRow>>,
+    pub examples: Vec<usize>,
+    pub images: Vec<(String, String)>,
 }
 
 impl Page {
+    pub fn add_row(&mut self, row: InlineKeyboardRow) {
+        if self.additionals.is_empty() {
+            self.additionals.push(vec![]);
+        }
+        for additional in &mut self.additionals {
+            additional.insert(0, row.clone());
+        }
+    }
+
     pub fn build_keyboard(&self, index: usize) -> Option<InlineKeyboardMarkup> {
         if let Some(page_keyboard) = &self.page_keyboard {
             let markup = InlineKeyboardMarkup::new_with_row(page_keyboard.clone());
@@ -43,46 +61,461 @@ impl Page {
 #[derive(Clone)]
 pub struct Documentation {
     pub pages: Vec<Page>,
+    pub sections: Vec<(String, usize)>,
+    pub listings: Vec<(String, usize)>,
+    pub start: usize,
+    pub links: Vec<DocKey>,
 }
 
-pub fn fetch_documentation(path: &DocPath) -> Result<Option<Documentation>, ureq::Error> {
+impl Documentation {
+    fn find_section(&self, anchor: &str) -> Option<usize> {
+        let anchor = anchor.to_lowercase();
+        self.sections
+            .iter()
+            .find(|(heading, _)| heading_anchor(heading) == anchor)
+            .map(|(_, page)| *page)
+    }
+}
+
+fn heading_anchor(heading: &str) -> String {
+    heading
+        .to_lowercase()
+        .split_whitespace()
+        .map(|word| {
+            word.chars()
+                .filter(|&c| c.is_alphanumeric() || c == '-' || c == '_')
+                .collect::<String>()
+        })
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+pub fn fetch_documentation(
+    path: &DocPath,
+    show_hidden: bool,
+    renderer: &dyn Renderer,
+) -> Result<Option<Documentation>, ureq::Error> {
+    let (url, html) = match fetch_html(path)? {
+        Some(page) => page,
+        None => return Ok(None),
+    };
+    let member = path
+        .fragment()
+        .and_then(|fragment| find_member(&html, fragment));
+    let mut documentation = render_documentation(&html, &url, renderer, |writer, document| {
+        writer.show_hidden = show_hidden;
+        writer.full_examples = find_full_examples(&html);
+        if let Some(member) = &member {
+            writer.write_member(&document.title, member, &url);
+        }
+    });
+    if let Some(documentation) = &mut documentation {
+        for page in &mut documentation.pages {
+            if !page.examples.is_empty() {
+                let (text, callback_data) = if show_hidden {
+                    ("Hide full example", "h")
+                } else {
+                    ("Show full example", "e")
+                };
+                page.add_row(InlineKeyboardRow::new_emplace(
+                    text,
+                    InlineKeyboardButtonKind::Callback {
+                        callback_data: callback_data.into(),
+                    },
+                ));
+            }
+            if !page.images.is_empty() {
+                page.add_row(InlineKeyboardRow::new_emplace(
+                    format!("Images ({})", page.images.len()),
+                    InlineKeyboardButtonKind::Callback {
+                        callback_data: "i".into(),
+                    },
+                ));
+            }
+        }
+        if find_source_link(&html, path.fragment()).is_some() {
+            documentation.pages[0].add_row(InlineKeyboardRow::new_emplace(
+                "Source",
+                InlineKeyboardButtonKind::Callback {
+                    callback_data: "s".into(),
+                },
+            ));
+        }
+        if documentation.sections.len() > 1 {
+            let sections = documentation.sections.clone();
+            add_sections_keyboard(&mut documentation.pages[0], &sections);
+        }
+        if let Some(start) = path
+            .fragment()
+            .and_then(|fragment| documentation.find_section(fragment))
+        {
+            documentation.start = start;
+        }
+    }
+    Ok(documentation)
+}
+
+pub fn fetch_html(path: &DocPath) -> Result<Option<(Url, String)>, ureq::Error> {
     let candidates = path.docs_url();
     for url in candidates {
-        match ureq::get(&url).call() {
-            Ok(response) => {
-                if response.status() == 200 {
-                    let url = Url::parse(response.get_url()).unwrap();
-                    let result = response
-                        .into_string()
-                        .ok()
-                        .as_deref()
-                        .map(Html::parse_document)
-                        .as_ref()
-                        .and_then(parse_document)
-                        .map(|doc| build_documentation(doc, &url));
-                    return Ok(result);
+        if let Some(page) = fetch_page(&url)? {
+            return Ok(Some(page));
+        }
+    }
+    Ok(None)
+}
+
+pub fn fetch_page(url: &str) -> Result<Option<(Url, String)>, ureq::Error> {
+    match ureq::get(url).call() {
+        Ok(response) if response.status() == 200 => {
+            let url = Url::parse(response.get_url()).unwrap();
+            Ok(response.into_string().ok().map(|html| (url, html)))
+        }
+        Err(e @ ureq::Error::Transport(_)) => Err(e),
+        _ => Ok(None),
+    }
+}
+
+pub fn render_documentation(
+    html: &str,
+    url: &Url,
+    renderer: &dyn Renderer,
+    preface: impl FnOnce(&mut AutoPaginateWriter, &Document),
+) -> Option<Documentation> {
+    let annotations = find_annotations(html);
+    let html = Html::parse_document(html);
+    parse_document(&html)
+        .map(|document| build_documentation(document, url, &annotations, renderer, preface))
+}
+
+pub fn build_pages(
+    renderer: &dyn Renderer,
+    write: impl FnOnce(&mut AutoPaginateWriter),
+) -> Documentation {
+    let mut pages = vec![];
+    let mut writer = AutoPaginateWriter::new(&mut pages, renderer);
+    write(&mut writer);
+    writer.finalize();
+    Documentation {
+        pages,
+        sections: vec![],
+        listings: vec![],
+        start: 0,
+        links: vec![],
+    }
+}
+
+struct Member {
+    id: String,
+    name: String,
+    signature: String,
+    summary: String,
+}
+
+fn find_member(html: &str, id: &str) -> Option<Member> {
+    let (_, name) = id.split_once('.')?;
+    let begin = html.find(&format!("id=\"{}\"", id))?;
+    let rest = &html[begin..];
+    let end = rest[1..].find(" id=\"").map_or(rest.len(), |end| end + 1);
+    let section = &rest[..end];
+    let header = Regex::new(r#"(?s)<h[34][^>]*class="code-header"[^>]*>(.*?)</h[34]>"#)
+        .unwrap()
+        .captures(section)
+        .or_else(|| {
+            Regex::new(r#"(?s)<code>(.*?)</code>"#)
+                .unwrap()
+                .captures(section)
+        })?;
+    let summary = Regex::new(r#"(?s)<div class="docblock[^"]*">.*?<p>(.*?)</p>"#)
+        .unwrap()
+        .captures(section)
+        .map(|captures| strip_tags(&captures[1]))
+        .unwrap_or_default();
+    Some(Member {
+        id: id.into(),
+        name: name.into(),
+        signature: strip_tags(&header[1]),
+        summary,
+    })
+}
+
+pub fn find_source_link(html: &str, fragment: Option<&str>) -> Option<String> {
+    let html = match fragment.filter(|id| id.contains('.')) {
+        Some(id) => &html[html.find(&format!("id=\"{}\"", id))?..],
+        None => html,
+    };
+    let tag = Regex::new(r#"<a [^>]*class="src(?:link)?"[^>]*>"#)
+        .unwrap()
+        .find(html)?;
+    Regex::new(r#"href="([^"]+)""#)
+        .unwrap()
+        .captures(tag.as_str())
+        .map(|captures| captures[1].replace("&amp;", "&"))
+}
+
+pub struct Outline {
+    pub declaration: Option<String>,
+    pub members: Vec<(String, String)>,
+}
+
+pub fn outline(html: &str) -> Outline {
+    let declaration = parse_document(&Html::parse_document(html))
+        .and_then(|document| document.declaration.as_deref().map(text_parts_to_plain));
+    let mut members: Vec<(String, String)> = vec![];
+    for captures in Regex::new(
+        r#"id="((?:method|tymethod|variant|structfield|associatedtype|associatedconstant)\.[^"]+)""#,
+    )
+    .unwrap()
+    .captures_iter(html)
+    {
+        let id = &captures[1];
+        if members.iter().any(|(member, _)| member == id) {
+            continue;
+        }
+        if let Some(member) = find_member(html, id) {
+            members.push((member.id, member.signature));
+        }
+    }
+    Outline {
+        declaration,
+        members,
+    }
+}
+
+pub fn strip_tags(html: &str) -> String {
+    Regex::new("<[^>]*>")
+        .unwrap()
+        .replace_all(html, "")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
+
+#[derive(Default)]
+struct Annotations {
+    badges: Vec<String>,
+    row_badges: HashMap<String, Vec<String>>,
+    tables: Vec<String>,
+}
+
+fn find_annotations(html: &str) -> Annotations {
+    let mut annotations = Annotations::default();
+
+    let header_end = top_docblock(html)
+        .map(|(start, _)| start)
+        .or_else(|| html.find("<h2"))
+        .unwrap_or(html.len());
+    let header = &html[..header_end];
+    let item_info = Regex::new(r#"<(?:div|span) class="item-info""#)
+        .unwrap()
+        .find(header)
+        .and_then(|tag| block_end(html, tag.start()).map(|end| &html[tag.start()..end]))
+        .unwrap_or_default();
+    let top = Regex::new(r#"<span class="emoji">[^<]*</span>"#)
+        .unwrap()
+        .replace_all(item_info, "");
+    if let Some(captures) = Regex::new(r#"(?s)<div class="stab deprecated">(.*?)</div>"#)
+        .unwrap()
+        .captures(&top)
+    {
+        annotations
+            .badges
+            .push(format!("⚠️ {}", strip_tags(&captures[1]).trim()));
+    }
+    if let Some(captures) = Regex::new(r#"(?s)<div class="stab unstable">(.*?)</div>"#)
+        .unwrap()
+        .captures(&top)
+    {
+        let badge = match Regex::new("<code>([^<]+)</code>")
+            .unwrap()
+            .captures(&captures[1])
+        {
+            Some(feature) => format!("🧪 Nightly-only feature({})", strip_tags(&feature[1])),
+            None => "🧪 Nightly-only".to_string(),
+        };
+        annotations.badges.push(badge);
+    }
+    if let Some(captures) = Regex::new(r#"(?s)<div class="stab portability">(.*?)</div>"#)
+        .unwrap()
+        .captures(&top)
+    {
+        annotations
+            .badges
+            .push(format!("🏷️ {}", strip_tags(&captures[1]).trim()));
+    }
+    if let Some(captures) = Regex::new(r#"(?s)<pre class="rust[^"]*">(.*?)</pre>"#)
+        .unwrap()
+        .captures(header)
+    {
+        let declaration = strip_tags(&captures[1]);
+        let signature = declaration
+            .split(['{', '(', '='])
+            .next()
+            .unwrap_or_default();
+        let words: Vec<_> = signature.split_whitespace().collect();
+        if words.contains(&"unsafe") {
+            annotations.badges.push("🔒 unsafe".into());
+        }
+        let qualifiers = words.split(|word| *word == "fn").next().unwrap_or_default();
+        if words.contains(&"fn") && qualifiers.contains(&"const") {
+            annotations.badges.push("⚡ const".into());
+        }
+    }
+
+    for captures in Regex::new(
+        r#"<(?:span class="stab (portability|deprecated|unstable)" title="([^"]*)"|sup title="unsafe [^"]*")"#,
+    )
+    .unwrap()
+    .captures_iter(html)
+    {
+        let before = &html[..captures.get(0).unwrap().start()];
+        if let Some(href) = before
+            .rfind("href=\"")
+            .map(|begin| &before[begin + 6..])
+            .and_then(|href| href.split('"').next())
+        {
+            let badge = match captures.get(1).map(|kind| kind.as_str()) {
+                Some("portability") => {
+                    format!("🏷️ {}", strip_tags(&captures[2]).replace('`', ""))
                 }
+                Some("deprecated") => "⚠️ Deprecated".into(),
+                Some(_) => "🧪 Nightly-only".into(),
+                None => "🔒 unsafe".into(),
+            };
+            let badges = annotations.row_badges.entry(href.into()).or_default();
+            if !badges.contains(&badge) {
+                badges.push(badge);
             }
-            Err(e @ ureq::Error::Transport(_)) => return Err(e),
-            _ => {}
         }
     }
-    Ok(None)
+
+    if let Some((start, end)) = top_docblock(html) {
+        annotations.tables = Regex::new(r"(?s)<table[^>]*>(.*?)</table>")
+            .unwrap()
+            .captures_iter(&html[start..end])
+            .map(|table| render_table(&table[1]))
+            .collect();
+    }
+
+    annotations
+}
+
+fn top_docblock(html: &str) -> Option<(usize, usize)> {
+    let first_section = html.find("<h2").unwrap_or(html.len());
+    let class = Regex::new(r#"class="docblock[^"]*""#)
+        .unwrap()
+        .find_iter(&html[..first_section])
+        .find(|class| !class.as_str().contains("item-decl"))?;
+    let start = html[..class.start()].rfind('<')?;
+    block_end(html, start).map(|end| (start, end))
 }
 
-fn build_documentation(document: Document, url: &Url) -> Documentation {
+fn block_end(html: &str, start: usize) -> Option<usize> {
+    let mut depth = 0;
+    for tag in Regex::new(r"<(/?)(?:div|span)\b")
+        .unwrap()
+        .captures_iter(&html[start..])
+    {
+        if tag[1].is_empty() {
+            depth += 1;
+        } else {
+            depth -= 1;
+            if depth == 0 {
+                return Some(start + tag.get(0).unwrap().start());
+            }
+        }
+    }
+    None
+}
+
+const MAX_COLUMN_WIDTH: usize = 24;
+
+fn render_table(html: &str) -> String {
+    let cell = Regex::new(r"(?s)<t[hd][^>]*>(.*?)</t[hd]>").unwrap();
+    let rows: Vec<Vec<String>> = Regex::new(r"(?s)<tr[^>]*>(.*?)</tr>")
+        .unwrap()
+        .captures_iter(html)
+        .map(|row| {
+            cell.captures_iter(&row[1])
+                .map(|cell| {
+                    let text = strip_tags(&cell[1]);
+                    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+                    if text.chars().count() > MAX_COLUMN_WIDTH {
+                        let mut text: String = text.chars().take(MAX_COLUMN_WIDTH - 1).collect();
+                        text.push('…');
+                        text
+                    } else {
+                        text
+                    }
+                })
+                .collect()
+        })
+        .collect();
+
+    let mut widths = vec![];
+    for row in &rows {
+        for (i, cell) in row.iter().enumerate() {
+            let width = cell.chars().count();
+            match widths.get_mut(i) {
+                Some(max) if *max < width => *max = width,
+                Some(_) => {}
+                None => widths.push(width),
+            }
+        }
+    }
+
+    let mut lines = vec![];
+    for (i, row) in rows.iter().enumerate() {
+        let line: Vec<_> = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{:width$}", cell, width = width))
+            .collect();
+        lines.push(line.join(" │ ").trim_end().to_string());
+        if i == 0 && html.contains("<thead") {
+            let rule: Vec<_> = widths.iter().map(|width| "─".repeat(*width)).collect();
+            lines.push(rule.join("─┼─"));
+        }
+    }
+    lines.join("\n")
+}
+
+fn build_documentation(
+    document: Document,
+    url: &Url,
+    annotations: &Annotations,
+    renderer: &dyn Renderer,
+    preface: impl FnOnce(&mut AutoPaginateWriter, &Document),
+) -> Documentation {
     let mut pages = vec![];
 
     let mut main_additionals = vec![];
+    let mut sections = vec![];
+    let mut listings = vec![];
 
     {
-        let mut writer = AutoPaginateWriter::new(&mut pages);
+        let mut writer = AutoPaginateWriter::new(&mut pages, renderer);
+        writer.tables = annotations.tables.clone();
+
+        preface(&mut writer, &document);
+        writer.new_page();
 
         if let Some(declaration) = &document.declaration {
-            writer.write_title(&document.title, url);
-            writer.line_break();
-            writer.line_break();
-            writer.write(declaration, url);
+            writer.write_section(&document.title, url, |writer| {
+                if !annotations.badges.is_empty() {
+                    writer.write_badges(&annotations.badges);
+                    writer.line_break();
+                    writer.line_break();
+                }
+                writer.write_code_block(declaration);
+            });
+        } else if !annotations.badges.is_empty() {
+            writer.write_section(&document.title, url, |writer| {
+                writer.write_badges(&annotations.badges);
+            });
         }
 
         if document.description.is_empty() {
@@ -90,6 +523,9 @@ fn build_documentation(document: Document, url: &Url) -> Documentation {
         }
 
         for description in &document.description {
+            if let Some(heading) = &description.heading {
+                sections.push((text_parts_to_plain(heading), writer.page_index()));
+            }
             writer.write_paragraphs(
                 description.heading.as_ref().unwrap_or(&document.title),
                 &description.contents,
@@ -105,8 +541,8 @@ fn build_documentation(document: Document, url: &Url) -> Documentation {
             match &item_list.kind {
                 paradocs::ListingType::Table(table) => {
                     let page_num = pages.len();
-                    let mut writer = AutoPaginateWriter::new(&mut pages);
-                    writer.write_item_rows(&item_list.heading, table, url);
+                    let mut writer = AutoPaginateWriter::new(&mut pages, renderer);
+                    writer.write_item_rows(&item_list.heading, table, &annotations.row_badges, url);
                     writer.finalize();
                     for page in &mut pages[page_num..] {
                         page.additionals.push(vec![InlineKeyboardRow::new_emplace(
@@ -116,15 +552,17 @@ fn build_documentation(document: Document, url: &Url) -> Documentation {
                             },
                         )]);
                     }
+                    let heading = text_parts_to_plain(&item_list.heading);
                     add_additional_autopage(
                         &mut main_additionals,
                         InlineKeyboardRow::new_emplace(
-                            text_parts_to_plain(&item_list.heading),
+                            &heading,
                             InlineKeyboardButtonKind::Callback {
                                 callback_data: page_num.to_string(),
                             },
                         ),
                     );
+                    listings.push((heading, page_num));
                 }
                 paradocs::ListingType::Fields(_) => {}
                 paradocs::ListingType::Impls(_) => {}
@@ -137,10 +575,68 @@ fn build_documentation(document: Document, url: &Url) -> Documentation {
         main_page.additionals = main_additionals.clone();
     }
 
-    Documentation { pages }
+    Documentation {
+        pages,
+        sections,
+        listings,
+        start: 0,
+        links: vec![],
+    }
+}
+
+fn add_sections_keyboard(page: &mut Page, sections: &[(String, usize)]) {
+    let base = page.additionals.len().max(1);
+    page.add_row(InlineKeyboardRow::new_emplace(
+        "Sections",
+        InlineKeyboardButtonKind::Callback {
+            callback_data: format!("x{}", base),
+        },
+    ));
+    let mut keyboard = vec![];
+    for (heading, index) in sections {
+        add_additional_autopage(
+            &mut keyboard,
+            InlineKeyboardRow::new_emplace(
+                heading,
+                InlineKeyboardButtonKind::Callback {
+                    callback_data: index.to_string(),
+                },
+            ),
+        );
+    }
+    let len = keyboard.len();
+    for (i, mut rows) in keyboard.into_iter().enumerate() {
+        let mut row = InlineKeyboardRow::new_emplace(
+            "« Back",
+            InlineKeyboardButtonKind::Callback {
+                callback_data: "x0".into(),
+            },
+        );
+        if i + 1 < len {
+            row = row.emplace(
+                "↓",
+                InlineKeyboardButtonKind::Callback {
+                    callback_data: format!("x{}", base + i + 1),
+                },
+            );
+        }
+        if i > 0 {
+            row = row.emplace(
+                "↑",
+                InlineKeyboardButtonKind::Callback {
+                    callback_data: format!("x{}", base + i - 1),
+                },
+            );
+        }
+        rows.push(row);
+        page.additionals.push(rows);
+    }
 }
 
-fn add_additional_autopage(additionals: &mut Vec<Vec<InlineKeyboardRow>>, row: InlineKeyboardRow) {
+pub fn add_additional_autopage(
+    additionals: &mut Vec<Vec<InlineKeyboardRow>>,
+    row: InlineKeyboardRow,
+) {
     if let Some(last_page) = additionals.last_mut() {
         if last_page.len() >= 3 {
             additionals.push(vec![row]);
@@ -152,7 +648,7 @@ fn add_additional_autopage(additionals: &mut Vec<Vec<InlineKeyboardRow>>, row: I
     }
 }
 
-fn add_additional_pager(additionals: &mut Vec<Vec<InlineKeyboardRow>>) {
+pub fn add_additional_pager(additionals: &mut Vec<Vec<InlineKeyboardRow>>) {
     let len = additionals.len();
     if len > 1 {
         for (i, additional) in additionals.iter_mut().enumerate() {
@@ -189,6 +685,78 @@ fn add_additional_pager(additionals: &mut Vec<Vec<InlineKeyboardRow>>) {
     }
 }
 
+fn find_full_examples(html: &str) -> HashMap<String, String> {
+    let html = Html::parse_document(html);
+    let pre = Selector::parse("pre.rust-example-rendered").unwrap();
+    let run = Selector::parse("a.test-arrow").unwrap();
+    let mut examples = HashMap::new();
+    for example in html.select(&Selector::parse("div.example-wrap").unwrap()) {
+        let code = match example.select(&pre).next() {
+            Some(pre) => trim_example(&pre.text().collect::<String>()),
+            None => continue,
+        };
+        let full = example
+            .select(&run)
+            .next()
+            .and_then(|run| Url::parse(run.value().attr("href")?).ok())
+            .and_then(|url| {
+                url.query_pairs()
+                    .find(|(key, _)| key == "code")
+                    .map(|(_, code)| unwrap_playground(&code))
+            });
+        if let Some(full) = full.filter(|full| *full != code) {
+            examples.insert(code, full);
+        }
+    }
+    examples
+}
+
+fn unwrap_playground(code: &str) -> String {
+    let code = code.strip_prefix("#![allow(unused)]\n").unwrap_or(code);
+    let body = code
+        .strip_prefix("fn main() {\n")
+        .and_then(|body| body.trim_end().strip_suffix('}'));
+    trim_example(body.unwrap_or(code))
+}
+
+fn trim_example(code: &str) -> String {
+    let mut lines: Vec<_> = code.lines().collect();
+    while lines.first().is_some_and(|line| line.trim().is_empty()) {
+        lines.remove(0);
+    }
+    while lines.last().is_some_and(|line| line.trim().is_empty()) {
+        lines.pop();
+    }
+    let indent = lines
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    let lines: Vec<_> = lines
+        .iter()
+        .map(|line| line.get(indent..).unwrap_or_default())
+        .collect();
+    lines.join("\n")
+}
+
+pub fn linked_title<'a>(title: &'a str, url: &'a Url) -> [TextPart<'a>; 5] {
+    [
+        TextPart::BeginStyle(TextStyle::Bold),
+        TextPart::BeginStyle(TextStyle::Link(url.as_str())),
+        TextPart::Text(title),
+        TextPart::EndStyle,
+        TextPart::EndStyle,
+    ]
+}
+
+fn first_link<'a>(parts: &[TextPart<'a>]) -> Option<&'a str> {
+    parts.iter().find_map(|part| match part {
+        TextPart::BeginStyle(TextStyle::Link(href)) => Some(*href),
+        _ => None,
+    })
+}
+
 fn text_parts_to_plain(parts: &[TextPart]) -> String {
     let mut buffer = String::new();
     let mut depth = 0;
@@ -221,94 +789,154 @@ fn text_parts_to_plain(parts: &[TextPart]) -> String {
     buffer
 }
 
-struct AutoPaginateWriter<'a> {
+const MAX_CAPTION: usize = 1024;
+
+// Telegram measures the message limit after parsing entities, so markup and
+// escapes are free and `written` only counts visible text; the default merely
+// keeps a margin below the cap.
+const DEFAULT_LIMIT: usize = 3800;
+const MAX_LIMIT: usize = 4096;
+
+fn page_limit() -> usize {
+    env::var("PAGE_LIMIT")
+        .ok()
+        .and_then(|limit| limit.parse().ok())
+        .map_or(DEFAULT_LIMIT, |limit: usize| limit.min(MAX_LIMIT))
+}
+
+fn text_len(text: &str) -> usize {
+    text.encode_utf16().count()
+}
+
+fn truncate_units(text: &str, limit: usize) -> &str {
+    let mut units = 0;
+    let end = text
+        .char_indices()
+        .find(|(_, c)| {
+            units += c.len_utf16();
+            units > limit
+        })
+        .map_or(text.len(), |(end, _)| end);
+    &text[..end]
+}
+
+fn split_point(text: &str, room: usize, in_code: bool, at_page_start: bool) -> (usize, usize) {
+    let end = truncate_units(text, room).len();
+    let separator = if in_code { '\n' } else { ' ' };
+    match text[..end].rfind(separator) {
+        Some(at) => (at, 1),
+        None if at_page_start => (end.max(text.chars().next().map_or(0, char::len_utf8)), 0),
+        None => (0, 0),
+    }
+}
+
+pub struct AutoPaginateWriter<'a> {
     pages: &'a mut Vec<Page>,
     buffer: String,
     styles: Vec<(String, String)>,
     in_code: bool,
+    renderer: &'a dyn Renderer,
+    code_tags: (&'static str, &'static str),
+    ignored_styles: usize,
     limit: usize,
     written: usize,
+    heading: Option<(String, usize)>,
+    show_hidden: bool,
+    full_examples: HashMap<String, String>,
+    examples: Vec<usize>,
+    example_index: usize,
+    example: Option<usize>,
+    images: Vec<(String, String)>,
+    tables: Vec<String>,
+    table_index: usize,
 
     begin_page: usize,
 }
 
 impl<'a> AutoPaginateWriter<'a> {
-    fn new(pages: &'a mut Vec<Page>) -> Self {
+    fn new(pages: &'a mut Vec<Page>, renderer: &'a dyn Renderer) -> Self {
         let len = pages.len();
         Self {
             pages,
             buffer: String::new(),
             styles: vec![],
             in_code: false,
-            limit: 1000,
+            renderer,
+            code_tags: renderer.block(Block::InlineCode),
+            ignored_styles: 0,
+            limit: page_limit(),
             written: 0,
+            heading: None,
+            show_hidden: false,
+            full_examples: HashMap::new(),
+            examples: vec![],
+            example_index: 0,
+            example: None,
+            images: vec![],
+            tables: vec![],
+            table_index: 0,
 
             begin_page: len,
         }
     }
 
-    fn write_str(&mut self, text: &str) {
+    pub fn write_str(&mut self, text: &str) {
         let text = if self.in_code {
             text.into()
         } else {
             Regex::new("\\s+").unwrap().replace_all(text, " ")
         };
-        self.written += text.len();
-        self.buffer.push_str(&ParseMode::HTML.escape(text));
+        let mut text = &*text;
+        while self.heading.is_some() && self.written + text_len(text) > self.limit {
+            let room = self.limit.saturating_sub(self.written);
+            let at_page_start =
+                self.heading.as_ref().map(|(_, written)| *written) == Some(self.written);
+            let (at, skip) = split_point(text, room, self.in_code, at_page_start);
+            self.written += text_len(&text[..at]);
+            let escaped = self.renderer.escape(&text[..at], self.in_code);
+            self.buffer.push_str(&escaped);
+            self.split_page();
+            text = &text[at + skip..];
+        }
+        self.written += text_len(text);
+        let escaped = self.renderer.escape(text, self.in_code);
+        self.buffer.push_str(&escaped);
     }
 
-    fn apply_style(&mut self, style: &TextStyle, base_url: &Url) {
+    pub fn apply_style(&mut self, style: &TextStyle, base_url: &Url) {
         if self.in_code {
+            self.ignored_styles += 1;
             return;
         }
-        match style {
-            TextStyle::Link(href) => {
-                if let Ok(href) = Url::options().base_url(Some(base_url)).parse(href) {
-                    let href = href.as_str().replace('"', "\\\"");
-                    let open = format!("<a href=\"{}\">", href);
-                    let close = "</a>".to_string();
-                    self.buffer.push_str(&open);
-                    self.styles.push((open, close));
-                }
-            }
-            TextStyle::Bold => {
-                let open = "<b>";
-                let close = "</b>";
-                self.buffer.push_str(open);
-                self.styles.push((open.into(), close.into()));
-            }
-            TextStyle::Italic => {
-                let open = "<i>";
-                let close = "</i>";
-                self.buffer.push_str(open);
-                self.styles.push((open.into(), close.into()));
-            }
-            TextStyle::Underline => {
-                let open = "<u>";
-                let close = "</u>";
-                self.buffer.push_str(open);
-                self.styles.push((open.into(), close.into()));
-            }
-            TextStyle::Strikethrough => {
-                let open = "<s>";
-                let close = "</s>";
-                self.buffer.push_str(open);
-                self.styles.push((open.into(), close.into()));
-            }
+        let (open, close) = match style {
+            TextStyle::Link(href) => match Url::options().base_url(Some(base_url)).parse(href) {
+                Ok(href) => self.renderer.markup(Markup::Link(href.as_str())),
+                Err(_) => return,
+            },
+            TextStyle::Bold => self.renderer.markup(Markup::Bold),
+            TextStyle::Italic => self.renderer.markup(Markup::Italic),
+            TextStyle::Underline => self.renderer.markup(Markup::Underline),
+            TextStyle::Strikethrough => self.renderer.markup(Markup::Strikethrough),
             TextStyle::Monospaced => {
                 for (_, close) in self.styles.iter().rev() {
                     self.buffer.push_str(close);
                 }
-                self.buffer.push_str("<code>");
+                self.code_tags = self.renderer.block(Block::InlineCode);
+                self.buffer.push_str(self.code_tags.0);
                 self.in_code = true;
+                return;
             }
-        }
+        };
+        self.buffer.push_str(&open);
+        self.styles.push((open, close));
     }
 
-    fn remove_style(&mut self) {
-        if self.in_code {
+    pub fn remove_style(&mut self) {
+        if self.ignored_styles > 0 {
+            self.ignored_styles -= 1;
+        } else if self.in_code {
             self.in_code = false;
-            self.buffer.push_str("</code>");
+            self.buffer.push_str(self.code_tags.1);
             for (open, _) in self.styles.iter() {
                 self.buffer.push_str(open);
             }
@@ -317,7 +945,7 @@ impl<'a> AutoPaginateWriter<'a> {
         }
     }
 
-    fn write_title(&mut self, title: &[TextPart], base_url: &Url) {
+    pub fn write_title(&mut self, title: &[TextPart], base_url: &Url) {
         let tmp = std::mem::take(&mut self.styles);
         let in_code = self.in_code;
         self.in_code = false;
@@ -333,19 +961,38 @@ impl<'a> AutoPaginateWriter<'a> {
         self.in_code = in_code;
     }
 
-    fn write(&mut self, text: &[TextPart], base_url: &Url) {
+    pub fn write(&mut self, text: &[TextPart], base_url: &Url) {
         for part in text {
             match part {
                 TextPart::Text(text) => self.write_str(text),
                 TextPart::Image(src) => {
+                    if let Some(url) = Url::options()
+                        .base_url(Some(base_url))
+                        .parse(src)
+                        .ok()
+                        .filter(|url| !url.path().ends_with(".svg"))
+                    {
+                        let caption = text_parts_to_plain(text);
+                        let caption = truncate_units(caption.trim(), MAX_CAPTION);
+                        self.images.push((url.into(), caption.into()));
+                    }
                     self.apply_style(&TextStyle::Link(src), base_url);
                     self.write_str("(image)");
                     self.remove_style();
                 }
                 TextPart::Table => {
-                    self.apply_style(&TextStyle::Link(base_url.as_str()), base_url);
-                    self.write_str("(table)");
-                    self.remove_style();
+                    let table = self.tables.get(self.table_index).cloned();
+                    self.table_index += 1;
+                    match table {
+                        Some(table) if text_len(&table) <= self.limit => {
+                            self.write_block(Block::Preformatted, &table)
+                        }
+                        _ => {
+                            self.apply_style(&TextStyle::Link(base_url.as_str()), base_url);
+                            self.write_str("(table)");
+                            self.remove_style();
+                        }
+                    }
                 }
                 TextPart::BeginStyle(style) => self.apply_style(style, base_url),
                 TextPart::EndStyle => self.remove_style(),
@@ -353,134 +1000,277 @@ impl<'a> AutoPaginateWriter<'a> {
         }
     }
 
-    fn write_paragraphs(&mut self, title: &[TextPart], paragraphs: &[Paragraph], base_url: &Url) {
-        self.new_page();
+    pub fn write_code_block(&mut self, text: &[TextPart]) {
+        let code: String = text
+            .iter()
+            .filter_map(|part| match part {
+                TextPart::Text(text) => Some(*text),
+                _ => None,
+            })
+            .collect();
+        let code = trim_example(&code);
+        let full = self.full_examples.get(&code).cloned();
+        if full.is_some() {
+            self.examples.push(self.example_index);
+            self.example = Some(self.example_index);
+            self.example_index += 1;
+        }
+        match full {
+            Some(full) if self.show_hidden => self.write_block(Block::RustCode, &full),
+            _ => self.write_block(Block::RustCode, &code),
+        }
+        self.example = None;
+    }
 
-        let mut written_p = 0;
-        for paragraph in paragraphs {
-            let prev_buf = std::mem::take(&mut self.buffer);
-            let prev_written = self.written;
-            self.written = 0;
+    pub fn write_preformatted(&mut self, text: &str) {
+        self.write_block(Block::Preformatted, text);
+    }
 
-            if written_p == 0 {
-                self.write_title(title, base_url);
-                self.line_break();
-                self.line_break();
-            }
+    fn write_block(&mut self, block: Block, text: &str) {
+        for (_, close) in self.styles.iter().rev() {
+            self.buffer.push_str(close);
+        }
+        self.code_tags = self.renderer.block(block);
+        self.buffer.push_str(self.code_tags.0);
+        let in_code = std::mem::replace(&mut self.in_code, true);
+        self.write_str(text);
+        self.in_code = in_code;
+        self.buffer.push_str(self.code_tags.1);
+        self.code_tags = self.renderer.block(Block::InlineCode);
+        for (open, _) in self.styles.iter() {
+            self.buffer.push_str(open);
+        }
+    }
 
-            match paragraph {
-                Paragraph::Text(text) => {
-                    self.write(text, base_url);
-                }
-                Paragraph::List(list) => {
-                    for (i, text) in list.iter().enumerate() {
-                        if i > 0 {
-                            self.line_break();
-                        }
-                        self.write_str("• ");
-                        self.write(text, base_url);
+    pub fn write_paragraphs(
+        &mut self,
+        title: &[TextPart],
+        paragraphs: &[Paragraph],
+        base_url: &Url,
+    ) {
+        self.write_placed_paragraphs(title, paragraphs, base_url);
+    }
+
+    pub fn write_placed_paragraphs(
+        &mut self,
+        title: &[TextPart],
+        paragraphs: &[Paragraph],
+        base_url: &Url,
+    ) -> Vec<usize> {
+        self.write_entries(title, paragraphs, base_url, |writer, paragraph| {
+            writer.write_paragraph(paragraph, base_url)
+        })
+    }
+
+    pub fn write_paragraph(&mut self, paragraph: &Paragraph, base_url: &Url) {
+        match paragraph {
+            Paragraph::Text(text) => {
+                self.write(text, base_url);
+            }
+            Paragraph::List(list) => {
+                for (i, text) in list.iter().enumerate() {
+                    if i > 0 {
+                        self.line_break();
                     }
-                }
-                Paragraph::Code(text) => {
-                    self.apply_style(&TextStyle::Monospaced, base_url);
+                    self.write_str("• ");
                     self.write(text, base_url);
-                    self.remove_style();
                 }
             }
+            Paragraph::Code(text) => {
+                self.write_code_block(text);
+            }
+        }
+    }
+
+    pub fn write_item_rows(
+        &mut self,
+        title: &[TextPart],
+        rows: &[ItemRow],
+        row_badges: &HashMap<String, Vec<String>>,
+        base_url: &Url,
+    ) {
+        self.write_entries(title, rows, base_url, |writer, row| {
+            writer.write(&row.name, base_url);
+            if let Some(badges) = first_link(&row.name).and_then(|href| row_badges.get(href)) {
+                writer.write_str(" ");
+                writer.write_str(&badges.join(" "));
+            }
+            writer.line_break();
+            writer.write(&row.summary, base_url);
+        });
+    }
 
-            if written_p > 0 {
-                // 1 : line break
-                if self.written + prev_written + 1 > self.limit {
-                    self.pages.push(Page {
-                        text: prev_buf,
-                        page_keyboard: None,
-                        additionals: vec![],
-                    });
-                    let new_buf = std::mem::take(&mut self.buffer);
-                    self.write_title(title, base_url);
-                    self.line_break();
-                    self.line_break();
-                    self.buffer.push_str(&new_buf);
-                    written_p = 0;
+    pub fn write_section(
+        &mut self,
+        title: &[TextPart],
+        base_url: &Url,
+        write: impl Fn(&mut AutoPaginateWriter),
+    ) {
+        self.write_entries(title, &[()], base_url, |writer, _| write(writer));
+    }
+
+    pub fn write_entries<T>(
+        &mut self,
+        title: &[TextPart],
+        entries: &[T],
+        base_url: &Url,
+        write_entry: impl Fn(&mut Self, &T),
+    ) -> Vec<usize> {
+        self.new_page();
+
+        let (heading, heading_written, _, _) = self.render(|writer| {
+            writer.write_title(title, base_url);
+            writer.line_break();
+            writer.line_break();
+        });
+        let heading = (heading, heading_written);
+        let mut is_empty = true;
+        let mut placements = vec![];
+        for entry in entries {
+            let table_index = self.table_index;
+            let example_index = self.example_index;
+            let (text, written, examples, images) =
+                self.render(|writer| write_entry(writer, entry));
+            // 1 : line break
+            if !is_empty && self.written + 1 + written <= self.limit {
+                placements.push(self.pages.len());
+                self.line_break();
+                self.buffer.push_str(&text);
+                self.written += written;
+                self.examples.extend(examples);
+                self.images.extend(images);
+            } else {
+                self.new_page();
+                placements.push(self.pages.len());
+                self.buffer.push_str(&heading.0);
+                self.written = heading.1;
+                if heading.1 + written <= self.limit {
+                    self.buffer.push_str(&text);
+                    self.written += written;
+                    self.examples.extend(examples);
+                    self.images.extend(images);
                 } else {
-                    let new_buf = std::mem::replace(&mut self.buffer, prev_buf);
-                    self.line_break();
-                    self.buffer.push_str(&new_buf);
-                    self.written += prev_written + 1;
+                    self.heading = Some(heading.clone());
+                    self.table_index = table_index;
+                    self.example_index = example_index;
+                    write_entry(self, entry);
+                    self.heading = None;
                 }
             }
-            written_p += 1;
+            is_empty = false;
         }
+        placements
     }
 
-    fn write_item_rows(&mut self, title: &[TextPart], rows: &[ItemRow], base_url: &Url) {
-        self.new_page();
+    fn render(
+        &mut self,
+        write: impl FnOnce(&mut Self),
+    ) -> (String, usize, Vec<usize>, Vec<(String, String)>) {
+        let buffer = std::mem::take(&mut self.buffer);
+        let written = std::mem::replace(&mut self.written, 0);
+        let heading = self.heading.take();
+        let examples = std::mem::take(&mut self.examples);
+        let images = std::mem::take(&mut self.images);
+        write(self);
+        self.heading = heading;
+        (
+            std::mem::replace(&mut self.buffer, buffer),
+            std::mem::replace(&mut self.written, written),
+            std::mem::replace(&mut self.examples, examples),
+            std::mem::replace(&mut self.images, images),
+        )
+    }
 
-        let mut written_rows = 0;
-        for row in rows {
-            let prev_buf = std::mem::take(&mut self.buffer);
-            let prev_written = self.written;
-            self.written = 0;
+    fn split_page(&mut self) {
+        let (heading, written) = match &self.heading {
+            Some(heading) => heading.clone(),
+            None => return,
+        };
+        if self.in_code {
+            self.buffer.push_str(self.code_tags.1);
+        } else {
+            for (_, close) in self.styles.iter().rev() {
+                self.buffer.push_str(close);
+            }
+        }
+        self.new_page();
+        if self.in_code {
+            self.examples.extend(self.example);
+        }
+        self.buffer.push_str(&heading);
+        self.written = written;
+        if self.in_code {
+            self.buffer.push_str(self.code_tags.0);
+        } else {
+            for (open, _) in self.styles.iter() {
+                self.buffer.push_str(open);
+            }
+        }
+    }
 
-            if written_rows == 0 {
-                self.write_title(title, base_url);
-                self.line_break();
-                self.line_break();
+    fn write_member(&mut self, title: &[TextPart], member: &Member, base_url: &Url) {
+        let mut member_url = base_url.clone();
+        member_url.set_fragment(Some(&member.id));
+        self.write_section(title, base_url, |writer| {
+            writer.apply_style(&TextStyle::Link(member_url.as_str()), base_url);
+            writer.apply_style(&TextStyle::Bold, base_url);
+            writer.write_str(&member.name);
+            writer.remove_style();
+            writer.remove_style();
+            writer.line_break();
+            writer.line_break();
+            writer.write_code_block(&[TextPart::Text(&member.signature)]);
+            if !member.summary.is_empty() {
+                writer.line_break();
+                writer.line_break();
+                writer.write_str(&member.summary);
             }
+        });
+    }
 
-            self.write(&row.name, base_url);
-            self.line_break();
-            self.write(&row.summary, base_url);
-
-            if written_rows > 0 {
-                // 1 : line break
-                if self.written + prev_written + 1 > self.limit {
-                    self.pages.push(Page {
-                        text: prev_buf,
-                        page_keyboard: None,
-                        additionals: vec![],
-                    });
-                    let new_buf = std::mem::take(&mut self.buffer);
-                    self.write_title(title, base_url);
-                    self.line_break();
-                    self.line_break();
-                    self.buffer.push_str(&new_buf);
-                    written_rows = 0;
-                } else {
-                    let new_buf = std::mem::replace(&mut self.buffer, prev_buf);
-                    self.line_break();
-                    self.buffer.push_str(&new_buf);
-                    self.written += prev_written + 1;
-                }
+    fn write_badges(&mut self, badges: &[String]) {
+        for (i, badge) in badges.iter().enumerate() {
+            if i > 0 {
+                self.line_break();
             }
-            written_rows += 1;
+            self.write_str(badge);
         }
     }
 
-    fn line_break(&mut self) {
-        if self.written < self.limit {
+    pub fn line_break(&mut self) {
+        if self.heading.is_some() && self.written >= self.limit {
+            self.split_page();
+        } else if self.written < self.limit {
             self.buffer.push('\n');
             self.written += 1;
         }
     }
 
-    fn new_page(&mut self) {
+    fn page_index(&self) -> usize {
+        self.pages.len() + usize::from(!self.buffer.is_empty())
+    }
+
+    pub fn new_page(&mut self) {
         if !self.buffer.is_empty() {
             let text = std::mem::take(&mut self.buffer);
             self.pages.push(Page {
                 text,
                 page_keyboard: None,
                 additionals: vec![],
+                examples: std::mem::take(&mut self.examples),
+                images: std::mem::take(&mut self.images),
             });
         }
     }
 
-    fn finalize(self) {
+    pub fn finalize(self) {
         if !self.buffer.is_empty() {
             self.pages.push(Page {
                 text: self.buffer,
                 page_keyboard: None,
                 additionals: vec![],
+                examples: self.examples,
+                images: self.images,
             })
         }
 
@@ -540,3 +1330,305 @@ impl<'a> AutoPaginateWriter<'a> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::render::{HtmlRenderer, MarkdownRenderer};
+
+    const TITLE: &str = "Title";
+    const LINK: &str = "https://example.com/";
+
+    fn styled(pieces: &[(u8, String)]) -> Vec<TextPart<'_>> {
+        let mut parts = vec![];
+        for (style, text) in pieces {
+            let styles = match style {
+                0 => vec![],
+                1 => vec![TextStyle::Bold],
+                2 => vec![TextStyle::Italic],
+                3 => vec![TextStyle::Link(LINK)],
+                4 => vec![TextStyle::Monospaced],
+                _ => vec![TextStyle::Bold, TextStyle::Monospaced],
+            };
+            let depth = styles.len();
+            parts.extend(styles.into_iter().map(TextPart::BeginStyle));
+            parts.push(TextPart::Text(text));
+            parts.extend((0..depth).map(|_| TextPart::EndStyle));
+        }
+        parts
+    }
+
+    fn render(paragraphs: &[Paragraph], limit: usize) -> Vec<Page> {
+        render_with(&HtmlRenderer, paragraphs, limit)
+    }
+
+    fn render_with(renderer: &dyn Renderer, paragraphs: &[Paragraph], limit: usize) -> Vec<Page> {
+        let url = Url::parse(LINK).unwrap();
+        let mut pages = vec![];
+        let mut writer = AutoPaginateWriter::new(&mut pages, renderer);
+        writer.limit = limit;
+        writer.write_paragraphs(&[TextPart::Text(TITLE)], paragraphs, &url);
+        writer.finalize();
+        pages
+    }
+
+    fn tags_balanced(html: &str) -> bool {
+        let mut open = vec![];
+        for tag in Regex::new(r"<(/?)(\w+)[^>]*>").unwrap().captures_iter(html) {
+            if tag[1].is_empty() {
+                open.push(tag[2].to_string());
+            } else if open.pop().as_deref() != Some(&tag[2]) {
+                return false;
+            }
+        }
+        open.is_empty()
+    }
+
+    fn visible(text: &str) -> String {
+        text.chars().filter(|c| !c.is_whitespace()).collect()
+    }
+
+    fn text() -> impl Strategy<Value = String> {
+        "[a-z0-9가-힣😀<>&\"' ]{0,120}"
+    }
+
+    fn code() -> impl Strategy<Value = String> {
+        "[a-z0-9가-힣😀<>&{}();= \n]{0,400}"
+    }
+
+    fn paragraph() -> impl Strategy<Value = (bool, Vec<(u8, String)>)> {
+        prop_oneof![
+            prop::collection::vec((0u8..6, text()), 1..8).prop_map(|pieces| (false, pieces)),
+            code().prop_map(|code| (true, vec![(0, code)])),
+        ]
+    }
+
+    fn sample() -> Vec<Paragraph<'static>> {
+        vec![
+            Paragraph::Text(vec![
+                TextPart::Text("Escape _*[]()~>#+-=|{}.! and <&> "),
+                TextPart::BeginStyle(TextStyle::Bold),
+                TextPart::Text("bold"),
+                TextPart::EndStyle,
+                TextPart::Text(" "),
+                TextPart::BeginStyle(TextStyle::Link("https://example.com/a_(b)")),
+                TextPart::Text("link"),
+                TextPart::EndStyle,
+                TextPart::Text(" "),
+                TextPart::BeginStyle(TextStyle::Monospaced),
+                TextPart::Text("a`b\\c*"),
+                TextPart::EndStyle,
+            ]),
+            Paragraph::Code(vec![TextPart::Text("let s = \"`\\\" < 1;")]),
+        ]
+    }
+
+    #[test]
+    fn places_paragraphs_on_their_pages() {
+        let url = Url::parse(LINK).unwrap();
+        let texts = ["a".repeat(30), "b".repeat(30), "c".repeat(30)];
+        let paragraphs: Vec<_> = texts
+            .iter()
+            .map(|text| Paragraph::Text(vec![TextPart::Text(text)]))
+            .collect();
+        let mut pages = vec![];
+        let mut writer = AutoPaginateWriter::new(&mut pages, &HtmlRenderer);
+        writer.limit = 70;
+        let placements =
+            writer.write_placed_paragraphs(&[TextPart::Text(TITLE)], &paragraphs, &url);
+        writer.finalize();
+        assert_eq!(placements, [0, 0, 1]);
+        assert!(pages[1].text.contains(&texts[2]));
+    }
+
+    #[test]
+    fn renders_html() {
+        let pages = render_with(&HtmlRenderer, &sample(), 1000);
+        assert_eq!(pages.len(), 1);
+        assert_eq!(
+            pages[0].text,
+            concat!(
+                "Title\n\n",
+                r#"Escape _*[]()~&gt;#+-=|{}.! and &lt;&amp;&gt; <b>bold</b> "#,
+                r#"<a href="https://example.com/a_(b)">link</a> <code>a`b\c*</code>"#,
+                "\n",
+                r#"<pre><code class="language-rust">let s = "`\" &lt; 1;</code></pre>"#,
+            )
+        );
+    }
+
+    #[test]
+    fn renders_markdown() {
+        let pages = render_with(&MarkdownRenderer, &sample(), 1000);
+        assert_eq!(pages.len(), 1);
+        assert_eq!(
+            pages[0].text,
+            concat!(
+                "Title\n\n",
+                r#"Escape \_\*\[\]\(\)\~\>\#\+\-\=\|\{\}\.\! and <&\> *bold* "#,
+                r#"[link](https://example.com/a_(b\)) `a\`b\\c*`"#,
+                "\n",
+                "```rust\n",
+                r#"let s = "\`\\" < 1;"#,
+                "\n```",
+            )
+        );
+    }
+
+    #[test]
+    fn finds_full_examples_from_run_links() {
+        let html = r#"
+            <div class="example-wrap"><pre class="rust rust-example-rendered"><code>let x = 1;</code></pre><a class="test-arrow" href="https://play.rust-lang.org/?code=%23%21%5Ballow%28unused%29%5D%0Afn+main%28%29+%7B%0Alet+x+%3D+1%3B%0A%7D&amp;edition=2021">Run</a></div>
+            <div class="example-wrap"><pre class="rust rust-example-rendered"><code>let y = x + 1;</code></pre><a class="test-arrow" href="https://play.rust-lang.org/?code=%23%21%5Ballow%28unused%29%5D%0Afn+main%28%29+%7B%0Alet+x+%3D+1%3B%0Alet+y+%3D+x+%2B+1%3B%0A%7D&amp;edition=2021">Run</a></div>
+            <div class="example-wrap"><pre class="language-text"><code># not hidden</code></pre></div>
+        "#;
+        let examples = find_full_examples(html);
+        assert_eq!(examples.len(), 1);
+        assert_eq!(examples["let y = x + 1;"], "let x = 1;\nlet y = x + 1;");
+    }
+
+    #[test]
+    fn collects_tables_from_the_top_docblock() {
+        let html = r#"
+            <pre class="rust item-decl"><code>pub struct S;</code></pre>
+            <details class="toggle top-doc" open><summary></summary><div class="docblock">
+                <div><table><tr><td>top</td></tr></table></div>
+            </div></details>
+            <h2 id="implementations">Implementations</h2>
+            <div class="docblock"><table><tr><td>method</td></tr></table></div>
+        "#;
+        let tables = find_annotations(html).tables;
+        assert_eq!(tables.len(), 1);
+        assert!(tables[0].contains("top"));
+        assert!(find_annotations(&html[html.find("<h2").unwrap()..])
+            .tables
+            .is_empty());
+    }
+
+    #[test]
+    fn finds_badges_in_the_item_header() {
+        let html = r#"
+            <pre class="rust item-decl"><code>pub const unsafe fn f()</code></pre>
+            <span class="item-info"><div class="stab deprecated"><span class="emoji">👎</span><span>Deprecated since 1.0</span></div></span>
+            <details class="toggle top-doc" open><summary></summary><div class="docblock">Docs.</div></details>
+        "#;
+        assert_eq!(
+            find_annotations(html).badges,
+            ["⚠️ Deprecated since 1.0", "🔒 unsafe", "⚡ const"]
+        );
+
+        let html = r#"
+            <pre class="rust item-decl"><code>pub struct S;</code></pre>
+            <h2 id="implementations">Implementations</h2>
+            <div class="docblock"><div class="stab unstable">Nightly</div></div>
+            <pre class="rust"><code>pub unsafe fn g()</code></pre>
+        "#;
+        assert!(find_annotations(html).badges.is_empty());
+    }
+
+    #[test]
+    fn marks_pages_with_full_examples() {
+        let url = Url::parse(LINK).unwrap();
+        let paragraphs = [
+            Paragraph::Code(vec![TextPart::Text("let a = 1;")]),
+            Paragraph::Code(vec![TextPart::Text("let y = x + 1;")]),
+        ];
+        for show_hidden in [false, true] {
+            let mut pages = vec![];
+            let mut writer = AutoPaginateWriter::new(&mut pages, &HtmlRenderer);
+            writer.show_hidden = show_hidden;
+            writer
+                .full_examples
+                .insert("let y = x + 1;".into(), "let x = 1;\nlet y = x + 1;".into());
+            writer.write_paragraphs(&[TextPart::Text(TITLE)], &paragraphs, &url);
+            writer.finalize();
+            assert_eq!(pages[0].examples, [0]);
+            assert_eq!(pages[0].text.contains("let x = 1;"), show_hidden);
+        }
+    }
+
+    #[test]
+    fn text_len_counts_utf16_units() {
+        assert_eq!(text_len("abc"), 3);
+        assert_eq!(text_len("가나다"), 3);
+        assert_eq!(text_len("😀"), 2);
+        assert_eq!(text_len("가😀a"), 4);
+    }
+
+    #[test]
+    fn truncate_units_keeps_whole_characters() {
+        assert_eq!(truncate_units("가나다", 2), "가나");
+        assert_eq!(truncate_units("a😀b", 2), "a");
+        assert_eq!(truncate_units("a😀b", 3), "a😀");
+        assert_eq!(truncate_units("abc", 10), "abc");
+    }
+
+    #[test]
+    fn split_point_counts_cjk_by_units() {
+        assert_eq!(split_point("가나다 라마바", 4, false, false), (9, 1));
+        assert_eq!(split_point("가나다라", 2, false, true), (6, 0));
+        assert_eq!(split_point("가나다라", 2, false, false), (0, 0));
+    }
+
+    #[test]
+    fn split_point_counts_emoji_as_two_units() {
+        assert_eq!(split_point("😀😀 😀", 5, false, false), (8, 1));
+        assert_eq!(split_point("😀😀 😀", 4, false, false), (0, 0));
+        assert_eq!(split_point("😀😀 😀", 3, false, true), (4, 0));
+        assert_eq!(split_point("😀😀😀", 5, false, true), (8, 0));
+        assert_eq!(
+            split_point(
+                "😀
+😀😀",
+                5,
+                true,
+                false
+            ),
+            (4, 1)
+        );
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(64))]
+
+        #[test]
+        fn pages_are_balanced_bounded_and_lossless(
+            input in prop::collection::vec(paragraph(), 1..12),
+            limit in 60usize..400,
+        ) {
+            let paragraphs: Vec<_> = input
+                .iter()
+                .map(|(is_code, pieces)| {
+                    if *is_code {
+                        Paragraph::Code(vec![TextPart::Text(&pieces[0].1)])
+                    } else {
+                        Paragraph::Text(styled(pieces))
+                    }
+                })
+                .collect();
+            let pages = render(&paragraphs, limit);
+
+            let mut output = String::new();
+            for page in &pages {
+                prop_assert!(tags_balanced(&page.text), "unbalanced page: {:?}", page.text);
+                let text = strip_tags(&page.text);
+                prop_assert!(
+                    text_len(&text) <= limit,
+                    "page of {} units over {}: {:?}",
+                    text_len(&text),
+                    limit,
+                    text
+                );
+                prop_assert!(text.starts_with(TITLE));
+                output.push_str(&text[TITLE.len()..]);
+            }
+            let expected: String = input
+                .iter()
+                .flat_map(|(_, pieces)| pieces.iter().map(|(_, text)| visible(text)))
+                .collect();
+            prop_assert_eq!(visible(&output), expected);
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use std::collections::HashSet;
+
+use regex::Regex;
+use url::Url;
+
+use crate::path::DocPath;
+
+const PRELUDE: &[&str] = &[
+    "marker/trait.Copy.html",
+    "marker/trait.Send.html",
+    "marker/trait.Sized.html",
+    "marker/trait.Sync.html",
+    "marker/trait.Unpin.html",
+    "ops/trait.Drop.html",
+    "ops/trait.Fn.html",
+    "ops/trait.FnMut.html",
+    "ops/trait.FnOnce.html",
+    "mem/fn.drop.html",
+    "boxed/struct.Box.html",
+    "borrow/trait.ToOwned.html",
+    "clone/trait.Clone.html",
+    "cmp/trait.PartialEq.html",
+    "cmp/trait.PartialOrd.html",
+    "cmp/trait.Eq.html",
+    "cmp/trait.Ord.html",
+    "convert/trait.AsRef.html",
+    "convert/trait.AsMut.html",
+    "convert/trait.Into.html",
+    "convert/trait.From.html",
+    "convert/trait.TryFrom.html",
+    "convert/trait.TryInto.html",
+    "default/trait.Default.html",
+    "iter/trait.Iterator.html",
+    "iter/trait.Extend.html",
+    "iter/trait.IntoIterator.html",
+    "iter/trait.DoubleEndedIterator.html",
+    "iter/trait.ExactSizeIterator.html",
+    "iter/trait.FromIterator.html",
+    "option/enum.Option.html",
+    "result/enum.Result.html",
+    "string/struct.String.html",
+    "string/trait.ToString.html",
+    "vec/struct.Vec.html",
+    "macro.assert.html",
+    "macro.assert_eq.html",
+    "macro.dbg.html",
+    "macro.format.html",
+    "macro.panic.html",
+    "macro.println.html",
+    "macro.vec.html",
+    "macro.write.html",
+];
+
+const INDEXED_CRATES: &[&str] = &["std", "core", "alloc"];
+
+pub struct StdIndex {
+    items: Vec<(String, DocPath)>,
+}
+
+impl StdIndex {
+    pub fn fetch() -> Result<Self, ureq::Error> {
+        let link = Regex::new(r#"<a href="([^"]+\.html)">([^<]+)</a>"#).unwrap();
+        let mut items: Vec<(String, DocPath)> = vec![];
+        let mut seen = HashSet::new();
+        for crate_name in INDEXED_CRATES {
+            let base = Url::parse(&format!("https://doc.rust-lang.org/{}/", crate_name)).unwrap();
+            let html = ureq::get(base.join("all.html").unwrap().as_str())
+                .call()?
+                .into_string()?;
+            for captures in link.captures_iter(&html) {
+                let item_path = captures[2].to_string();
+                if seen.contains(&item_path) {
+                    continue;
+                }
+                let path = base.join(&captures[1]).ok();
+                if let Some(path) = path.as_ref().and_then(DocPath::from_url) {
+                    let name = item_path.rsplit("::").next().unwrap().to_string();
+                    items.push((name, path));
+                    seen.insert(item_path);
+                }
+            }
+        }
+        Ok(Self { items })
+    }
+
+    pub fn find(&self, name: &str) -> impl Iterator<Item = &DocPath> {
+        let name = name.to_string();
+        self.items
+            .iter()
+            .filter(move |(item_name, _)| *item_name == name)
+            .map(|(_, path)| path)
+    }
+}
+
+pub enum Resolution {
+    Found(DocPath),
+    Ambiguous(Vec<DocPath>),
+}
+
+pub fn resolve_path(index: Option<&StdIndex>, path: DocPath) -> Resolution {
+    if !path.is_bare() {
+        return Resolution::Found(path);
+    }
+
+    let name = path.item_name();
+    let with_fragment = |found: DocPath| match path.fragment() {
+        Some(fragment) => found.with_fragment(fragment),
+        None => found,
+    };
+    let mut candidates: Vec<_> = find_prelude(name).into_iter().collect();
+    if let Some(index) = index {
+        for found in index.find(name) {
+            if !candidates.contains(found) {
+                candidates.push(found.clone());
+            }
+        }
+    }
+    match candidates.len() {
+        0 => Resolution::Found(with_fragment(DocPath::crate_root(name))),
+        1 => Resolution::Found(with_fragment(candidates.pop().unwrap())),
+        _ => Resolution::Ambiguous(candidates.into_iter().map(with_fragment).collect()),
+    }
+}
+
+pub fn find_prelude(name: &str) -> Option<DocPath> {
+    let base = Url::parse("https://doc.rust-lang.org/std/").unwrap();
+    PRELUDE
+        .iter()
+        .filter_map(|item| base.join(item).ok())
+        .filter_map(|url| DocPath::from_url(&url))
+        .find(|path| path.item_name() == name)
+}
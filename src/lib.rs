@@ -0,0 +1,12 @@
+pub mod book;
+pub mod cargo;
+pub mod command;
+pub mod crates;
+pub mod db;
+pub mod diff;
+pub mod docs;
+pub mod index;
+pub mod lint;
+pub mod path;
+pub mod render;
+pub mod source;
@@ -0,0 +1,357 @@
+use regex::Regex;
+use serde_json::Value;
+use url::Url;
+
+use crate::{
+    book::{is_rust_code, parse_sections, write_blocks, Block, Span, Style},
+    crates::string,
+    docs::{build_pages, fetch_page, linked_title, Documentation},
+    render::Renderer,
+};
+
+const CLIPPY_URL: &str = "https://rust-lang.github.io/rust-clippy/stable/";
+
+const RUSTC_LEVELS: &[(&str, &str)] = &[
+    ("allow", "allowed-by-default"),
+    ("warn", "warn-by-default"),
+    ("deny", "deny-by-default"),
+];
+
+pub struct Lint {
+    pub name: String,
+    group: String,
+    level: String,
+    url: Url,
+    blocks: Vec<Block>,
+}
+
+pub struct LintIndex {
+    lints: Vec<Lint>,
+}
+
+impl LintIndex {
+    pub fn fetch() -> Result<Self, ureq::Error> {
+        let json = ureq::get(&format!("{}lints.json", CLIPPY_URL))
+            .call()?
+            .into_string()?;
+        let mut lints = parse_clippy_lints(&json);
+
+        for (level, page) in RUSTC_LEVELS {
+            let url = format!(
+                "https://doc.rust-lang.org/rustc/lints/listing/{}.html",
+                page
+            );
+            if let Some((url, html)) = fetch_page(&url)? {
+                lints.extend(parse_rustc_lints(&html, &url, level));
+            }
+        }
+
+        Ok(Self { lints })
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Lint> {
+        self.lints.iter().find(|lint| lint.name == name)
+    }
+
+    pub fn search(&self, query: &str) -> Vec<&Lint> {
+        let query = normalize(query);
+        if let Some(lint) = self.lints.iter().find(|lint| lint_key(lint) == query) {
+            return vec![lint];
+        }
+        let tolerance = (query.len() / 4).max(1);
+        let mut candidates: Vec<_> = self
+            .lints
+            .iter()
+            .filter_map(|lint| {
+                let key = lint_key(lint);
+                let distance = edit_distance(key, &query);
+                if key.contains(&query) || distance <= tolerance {
+                    Some((distance, lint))
+                } else {
+                    None
+                }
+            })
+            .collect();
+        candidates.sort_by_key(|(distance, _)| *distance);
+        candidates
+            .into_iter()
+            .take(8)
+            .map(|(_, lint)| lint)
+            .collect()
+    }
+}
+
+fn parse_clippy_lints(json: &str) -> Vec<Lint> {
+    let base = Url::parse(CLIPPY_URL).unwrap();
+    let entries = match serde_json::from_str(json) {
+        Ok(Value::Array(entries)) => entries,
+        _ => return vec![],
+    };
+    entries
+        .iter()
+        .filter_map(|entry| {
+            let id = string(&entry["id"])?;
+            let mut url = base.join("index.html").unwrap();
+            url.set_fragment(Some(&id));
+            Some(Lint {
+                name: format!("clippy::{}", id),
+                group: string(&entry["group"]).unwrap_or_default(),
+                level: string(&entry["level"]).unwrap_or_default(),
+                url,
+                blocks: parse_markdown(entry["docs"].as_str().unwrap_or_default()),
+            })
+        })
+        .collect()
+}
+
+fn parse_rustc_lints(html: &str, url: &Url, level: &str) -> Vec<Lint> {
+    let mut lints: Vec<Lint> = vec![];
+    for section in parse_sections(html) {
+        if section.level == 2 {
+            let mut url = url.clone();
+            url.set_fragment(section.anchor.as_deref());
+            lints.push(Lint {
+                name: section.heading.replace('-', "_"),
+                group: "rustc".into(),
+                level: level.to_string(),
+                url,
+                blocks: section.blocks,
+            });
+        } else if section.level > 2 {
+            if let Some(lint) = lints.last_mut() {
+                lint.blocks.push(heading_block(&section.heading));
+                lint.blocks.extend(section.blocks);
+            }
+        }
+    }
+    lints
+}
+
+fn lint_key(lint: &Lint) -> &str {
+    lint.name.strip_prefix("clippy::").unwrap_or(&lint.name)
+}
+
+fn normalize(query: &str) -> String {
+    let query = query.trim().to_lowercase().replace('-', "_");
+    query.strip_prefix("clippy::").unwrap_or(&query).to_string()
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<_> = b.chars().collect();
+    let mut row: Vec<_> = (0..=b.len()).collect();
+    for (i, a) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, b) in b.iter().enumerate() {
+            let next = (diagonal + usize::from(a != *b))
+                .min(row[j] + 1)
+                .min(row[j + 1] + 1);
+            diagonal = row[j + 1];
+            row[j + 1] = next;
+        }
+    }
+    row[b.len()]
+}
+
+fn heading_block(heading: &str) -> Block {
+    Block::Text(vec![
+        Span::Begin(Style::Bold),
+        Span::Text(heading.into()),
+        Span::End,
+    ])
+}
+
+fn parse_markdown(docs: &str) -> Vec<Block> {
+    let mut blocks = vec![];
+    let mut paragraph = String::new();
+    let mut list: Vec<String> = vec![];
+    let mut code: Option<(bool, String)> = None;
+    for line in docs.lines() {
+        if let Some((is_rust, buffer)) = &mut code {
+            if line.trim_start().starts_with("```") {
+                if *is_rust {
+                    blocks.push(Block::Code(strip_hidden_lines(buffer.trim_end())));
+                } else {
+                    blocks.push(Block::Preformatted(buffer.trim_end().into()));
+                }
+                code = None;
+            } else {
+                buffer.push_str(line.strip_prefix(' ').unwrap_or(line));
+                buffer.push('\n');
+            }
+            continue;
+        }
+
+        let line = line.trim();
+        if line.is_empty() || line.starts_with("```") || line.starts_with('#') {
+            flush_markdown(&mut blocks, &mut paragraph, &mut list);
+            if let Some(info) = line.strip_prefix("```") {
+                code = Some((info.is_empty() || is_rust_code(info), String::new()));
+            } else if !line.is_empty() {
+                blocks.push(heading_block(line.trim_start_matches('#').trim()));
+            }
+        } else if let Some(item) = line.strip_prefix("* ").or_else(|| line.strip_prefix("- ")) {
+            if !paragraph.is_empty() {
+                flush_markdown(&mut blocks, &mut paragraph, &mut list);
+            }
+            list.push(item.into());
+        } else if let Some(item) = list.last_mut() {
+            item.push(' ');
+            item.push_str(line);
+        } else {
+            if !paragraph.is_empty() {
+                paragraph.push(' ');
+            }
+            paragraph.push_str(line);
+        }
+    }
+    flush_markdown(&mut blocks, &mut paragraph, &mut list);
+    blocks
+}
+
+fn strip_hidden_lines(code: &str) -> String {
+    code.lines()
+        .filter_map(|line| {
+            let indent = line.len() - line.trim_start().len();
+            let trimmed = &line[indent..];
+            if trimmed == "#" || trimmed.starts_with("# ") {
+                None
+            } else if trimmed.starts_with("##") {
+                Some(format!("{}{}", &line[..indent], &trimmed[1..]))
+            } else {
+                Some(line.to_string())
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn flush_markdown(blocks: &mut Vec<Block>, paragraph: &mut String, list: &mut Vec<String>) {
+    if !paragraph.is_empty() {
+        blocks.push(Block::Text(parse_inline(&std::mem::take(paragraph))));
+    }
+    if !list.is_empty() {
+        blocks.push(Block::List(
+            list.drain(..).map(|item| parse_inline(&item)).collect(),
+        ));
+    }
+}
+
+fn parse_inline(text: &str) -> Vec<Span> {
+    let link = Regex::new(r"\[([^\]]+)\]\(([^)]+)\)").unwrap();
+    let mut spans = vec![];
+    for (i, part) in text.split('`').enumerate() {
+        if i % 2 == 1 {
+            spans.push(Span::Begin(Style::Code));
+            spans.push(Span::Text(part.into()));
+            spans.push(Span::End);
+            continue;
+        }
+        let mut last = 0;
+        for captures in link.captures_iter(part) {
+            let whole = captures.get(0).unwrap();
+            spans.push(Span::Text(part[last..whole.start()].into()));
+            spans.push(Span::Begin(Style::Link(captures[2].into())));
+            spans.push(Span::Text(captures[1].into()));
+            spans.push(Span::End);
+            last = whole.end();
+        }
+        spans.push(Span::Text(part[last..].into()));
+    }
+    spans
+}
+
+pub fn render_lint(lint: &Lint, renderer: &dyn Renderer) -> Documentation {
+    let summary = Block::Text(vec![Span::Text(format!(
+        "Group: {} · Default level: {}",
+        lint.group, lint.level
+    ))]);
+    let mut blocks = vec![&summary];
+    blocks.extend(&lint.blocks);
+    build_pages(renderer, |writer| {
+        write_blocks(
+            writer,
+            &linked_title(&lint.name, &lint.url),
+            &blocks,
+            &lint.url,
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index() -> LintIndex {
+        LintIndex {
+            lints: parse_clippy_lints(include_str!("../tests/fixtures/lints.json")),
+        }
+    }
+
+    fn names(lints: Vec<&Lint>) -> Vec<&str> {
+        lints.iter().map(|lint| lint.name.as_str()).collect()
+    }
+
+    #[test]
+    fn parses_fixture() {
+        let index = index();
+        assert_eq!(index.lints.len(), 4);
+        let lint = index.get("clippy::needless_borrow").unwrap();
+        assert_eq!(lint.group, "style");
+        assert_eq!(lint.level, "warn");
+        assert_eq!(lint.url.fragment(), Some("needless_borrow"));
+        assert!(matches!(lint.blocks.as_slice(), [Block::Text(_), ..]));
+    }
+
+    #[test]
+    fn hides_lines_only_in_rust_blocks() {
+        let blocks = parse_markdown(
+            "```rust\n# fn main() {\nlet x = 1;\n## attribute\n# }\n```\n```toml\n# comment\n```",
+        );
+        let code: Vec<_> = blocks
+            .iter()
+            .filter_map(|block| match block {
+                Block::Code(code) => Some((true, code.as_str())),
+                Block::Preformatted(code) => Some((false, code.as_str())),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            code,
+            [(true, "let x = 1;\n# attribute"), (false, "# comment")]
+        );
+    }
+
+    #[test]
+    fn search_exact() {
+        let index = index();
+        assert_eq!(
+            names(index.search("needless_borrow")),
+            ["clippy::needless_borrow"]
+        );
+    }
+
+    #[test]
+    fn search_fuzzy() {
+        let index = index();
+        let found = names(index.search("needless-borow"));
+        assert_eq!(found.first(), Some(&"clippy::needless_borrow"));
+        assert!(!found.contains(&"clippy::unwrap_used"));
+    }
+
+    #[test]
+    fn search_clippy_prefix() {
+        let index = index();
+        assert_eq!(
+            names(index.search("clippy::Redundant-Clone")),
+            ["clippy::redundant_clone"]
+        );
+    }
+
+    #[test]
+    fn edit_distance_counts_edits() {
+        assert_eq!(edit_distance("borrow", "borrow"), 0);
+        assert_eq!(edit_distance("borow", "borrow"), 1);
+        assert_eq!(edit_distance("clone", "clown"), 2);
+    }
+}
@@ -1,31 +1,39 @@
-mod command;
-mod db;
-mod docs;
-mod path;
-
-use std::env;
+use std::{
+    collections::{HashMap, HashSet},
+    env,
+};
 
-use command::Command;
-use db::{DocumentStore, SessionStore};
-use docs::fetch_documentation;
-use path::{DocPath, DocPathParseError};
+use rsdocbot::{
+    book::{find_book, normalize_error_code, BookIndex},
+    cargo::is_subcommand,
+    command::Command,
+    crates::split_version,
+    db::{ChoiceStore, DocKey, DocumentStore, Session, SessionStore},
+    index::{resolve_path, Resolution, StdIndex},
+    lint::{render_lint, LintIndex},
+    path::{attribute_name, find_docs_link, DocPath},
+};
 use telbot_ureq::{
     polling::Polling,
     types::{
-        markup::ParseMode,
-        message::{EditMessageReplyMarkup, EditMessageText, Message},
+        file::{InputFileVariant, InputMedia, InputMediaPhoto},
+        markup::{InlineKeyboardButtonKind, InlineKeyboardMarkup, InlineKeyboardRow, ParseMode},
+        message::{EditMessageReplyMarkup, EditMessageText, Message, SendMediaGroup, SendPhoto},
         query::CallbackQuery,
         update::{Update, UpdateKind},
     },
     Api, Result,
 };
 
-use crate::db::Session;
-
 #[derive(Default)]
 pub struct Context {
     cached_docs: DocumentStore,
     sessions: SessionStore,
+    auto_expand: HashSet<i64>,
+    choices: ChoiceStore,
+    std_index: Option<StdIndex>,
+    lint_index: Option<LintIndex>,
+    book_indexes: HashMap<&'static str, BookIndex>,
 }
 
 fn main() {
@@ -58,87 +66,543 @@ fn on_message(api: &Api, message: &Message, ctx: &mut Context) -> Result<()> {
 
     let command = Command::new(text);
 
-    if command.label == "/docs" {
-        let name = command.rest().trim();
-        match DocPath::try_from(name) {
-            Ok(path) => {
-                if let Some(cached) = ctx.cached_docs.get(&path) {
-                    let page = &cached.pages[0];
-                    let request = message
-                        .reply_text(&page.text)
-                        .with_parse_mode(ParseMode::HTML)
-                        .allow_sending_without_reply()
-                        .disable_web_page_preview();
-                    api.send_json(&request)?;
-                } else {
-                    match fetch_documentation(&path) {
-                        Ok(None) => {
-                            let request = message.reply_text("Cannot find that item.");
-                            api.send_json(&request)?;
-                        }
-                        Ok(Some(doc)) => {
-                            ctx.cached_docs.insert(path.clone(), doc.clone());
-                            let page = &doc.pages[0];
-                            let mut request = message
-                                .reply_text(&page.text)
-                                .with_parse_mode(ParseMode::HTML)
-                                .allow_sending_without_reply()
-                                .disable_web_page_preview();
-                            if let Some(keyboard) = &page.build_keyboard(0) {
-                                request = request.with_reply_markup(keyboard.clone());
-                            }
-                            let message = api.send_json(&request)?;
-                            ctx.sessions.insert(
-                                message.chat.id,
-                                message.message_id,
-                                Session { page: 0, path },
-                            );
-                        }
-                        Err(e) => log::error!("cannot fetch documentation: {}", e),
+    match command.label {
+        "/docs" => {
+            let name = command.rest().trim();
+            if name.starts_with('#') {
+                match attribute_name(name) {
+                    Some(attribute) => {
+                        let key = DocKey::Attribute(attribute.into());
+                        reply_documentation(api, message, ctx, key)?;
+                    }
+                    None => {
+                        let request = message.reply_text("Usage: /docs #[attribute]");
+                        api.send_json(&request)?;
                     }
                 }
+                return Ok(());
+            }
+            reply_item_path(api, message, ctx, name, DocKey::Item)?;
+        }
+        "/crate" => {
+            let name = command.rest().trim();
+            if name.is_empty() {
+                let request = message.reply_text("Usage: /crate <crate name>");
+                api.send_json(&request)?;
+            } else {
+                reply_documentation(api, message, ctx, DocKey::Crate(name.into()))?;
+            }
+        }
+        "/features" => {
+            let (name, version) = split_version(command.rest().trim());
+            if name.is_empty() {
+                let request = message.reply_text("Usage: /features <crate name>[@version]");
+                api.send_json(&request)?;
+            } else {
+                let key = DocKey::Features(name.into(), version.map(String::from));
+                reply_documentation(api, message, ctx, key)?;
             }
-            Err(DocPathParseError::Empty) => {
-                let request = message.reply_text("Usage: /docs <item path>");
+        }
+        "/deps" => {
+            let (name, version) = split_version(command.rest().trim());
+            if name.is_empty() {
+                let request = message.reply_text("Usage: /deps <crate name>[@version]");
                 api.send_json(&request)?;
+            } else {
+                let key = DocKey::Dependencies(name.into(), version.map(String::from));
+                reply_documentation(api, message, ctx, key)?;
             }
-            Err(DocPathParseError::InvalidCharAt(_)) => {
-                let text = concat!(
-                    "*Item Path Format*\n",
-                    r"<crate name\>::<module1\>::<module2\>::…::<item name\>",
-                    "\n\n",
-                    r"every segment of the path should _only_ contain lowercase alphabets, ",
-                    r"underscore \(`\_`\), or hyphen \(`\-`\)\."
-                );
-                let request = message
-                    .reply_text(text)
-                    .allow_sending_without_reply()
-                    .with_parse_mode(ParseMode::MarkdownV2);
+        }
+        "/versions" => {
+            let name = command.rest().trim();
+            if name.is_empty() {
+                let request = message.reply_text("Usage: /versions <crate name>");
                 api.send_json(&request)?;
+            } else {
+                reply_documentation(api, message, ctx, DocKey::Versions(name.into()))?;
+            }
+        }
+        "/diff" => {
+            let mut args = command.args();
+            match (args.next(), args.next(), args.next()) {
+                (Some(name), Some(old), Some(new)) => match DocPath::try_from(name) {
+                    Ok(path) => {
+                        let key = DocKey::Diff(path, old.into(), new.into());
+                        reply_documentation(api, message, ctx, key)?;
+                    }
+                    Err(e) => {
+                        let request = message
+                            .reply_text(e.explain(name))
+                            .allow_sending_without_reply()
+                            .with_parse_mode(ParseMode::HTML);
+                        api.send_json(&request)?;
+                    }
+                },
+                _ => {
+                    let request =
+                        message.reply_text("Usage: /diff <item path> <old version> <new version>");
+                    api.send_json(&request)?;
+                }
+            }
+        }
+        "/error" => match normalize_error_code(command.rest().trim()) {
+            Some(code) => reply_documentation(api, message, ctx, DocKey::Error(code))?,
+            None => {
+                let request = message.reply_text("Usage: /error <error code, e.g. E0382>");
+                api.send_json(&request)?;
+            }
+        },
+        "/lint" => {
+            let query = command.rest().trim();
+            if query.is_empty() {
+                let request = message.reply_text("Usage: /lint <lint name>");
+                api.send_json(&request)?;
+                return Ok(());
+            }
+            let mut names = find_lints(ctx, query);
+            match names.len() {
+                0 => {
+                    let request = message.reply_text("Cannot find that lint.");
+                    api.send_json(&request)?;
+                }
+                1 => {
+                    let key = DocKey::Lint(names.pop().unwrap());
+                    reply_documentation(api, message, ctx, key)?;
+                }
+                _ => {
+                    let text = format!(
+                        "Several lints match <code>{}</code>. Which one do you mean?",
+                        ParseMode::HTML.escape(query)
+                    );
+                    let choices = names
+                        .into_iter()
+                        .map(|name| (name.clone(), DocKey::Lint(name)))
+                        .collect();
+                    reply_choices(api, message, ctx, text, choices)?;
+                }
+            }
+        }
+        "/book" | "/reference" | "/nomicon" | "/rbe" | "/cargobook" => {
+            reply_book_search(api, message, ctx, command.label, command.rest().trim())?;
+        }
+        "/cargo" => {
+            let mut args = command.args();
+            let key = match (args.next(), args.next()) {
+                (Some("manifest"), Some(key)) => Some(DocKey::Manifest(key.into())),
+                (Some("manifest"), None) => None,
+                (Some(subcommand), flag) if is_subcommand(subcommand) => {
+                    Some(DocKey::Cargo(subcommand.into(), flag.map(String::from)))
+                }
+                _ => None,
+            };
+            match key {
+                Some(key) => reply_documentation(api, message, ctx, key)?,
+                None => {
+                    let request = message.reply_text(
+                        "Usage: /cargo <subcommand> [flag]\n/cargo manifest <key or [table]>",
+                    );
+                    api.send_json(&request)?;
+                }
+            }
+        }
+        "/src" => {
+            let name = command.rest().trim();
+            if name.is_empty() {
+                let request = message.reply_text("Usage: /src <item path>");
+                api.send_json(&request)?;
+                return Ok(());
+            }
+            reply_item_path(api, message, ctx, name, DocKey::Source)?;
+        }
+        "/autoexpand" => {
+            let chat_id = message.chat.id;
+            let text = if ctx.auto_expand.remove(&chat_id) {
+                "Documentation links will no longer be expanded in this chat."
+            } else {
+                ctx.auto_expand.insert(chat_id);
+                "Documentation links posted in this chat will be expanded."
+            };
+            let request = message.reply_text(text).allow_sending_without_reply();
+            api.send_json(&request)?;
+        }
+        _ => {
+            if ctx.auto_expand.contains(&message.chat.id) {
+                if let Some(path) =
+                    find_docs_link(text).and_then(|link| DocPath::try_from(link).ok())
+                {
+                    reply_item(api, message, ctx, path, DocKey::Item)?;
+                }
             }
         }
     }
     Ok(())
 }
 
+fn resolve(ctx: &mut Context, path: DocPath) -> Resolution {
+    if path.is_bare() && ctx.std_index.is_none() {
+        match StdIndex::fetch() {
+            Ok(index) => ctx.std_index = Some(index),
+            Err(e) => log::error!("cannot fetch std index: {}", e),
+        }
+    }
+    resolve_path(ctx.std_index.as_ref(), path)
+}
+
+fn find_lints(ctx: &mut Context, query: &str) -> Vec<String> {
+    if ctx.lint_index.is_none() {
+        match LintIndex::fetch() {
+            Ok(index) => ctx.lint_index = Some(index),
+            Err(e) => log::error!("cannot fetch lint index: {}", e),
+        }
+    }
+    let index = match &ctx.lint_index {
+        Some(index) => index,
+        None => return vec![],
+    };
+
+    let lints = index.search(query);
+    for lint in &lints {
+        let key = DocKey::Lint(lint.name.clone());
+        if ctx.cached_docs.get(&key).is_none() {
+            let doc = render_lint(lint, ctx.cached_docs.renderer());
+            ctx.cached_docs.insert(key, doc);
+        }
+    }
+    lints.iter().map(|lint| lint.name.clone()).collect()
+}
+
+fn reply_item_path(
+    api: &Api,
+    message: &Message,
+    ctx: &mut Context,
+    name: &str,
+    key: fn(DocPath) -> DocKey,
+) -> Result<()> {
+    let (name, fragment) = match name.split_once('#') {
+        Some((name, fragment)) => (name, Some(fragment)),
+        None => (name, None),
+    };
+    match DocPath::try_from(name) {
+        Ok(path) => {
+            let path = match fragment {
+                Some(fragment) => path.with_fragment(fragment),
+                None => path,
+            };
+            reply_item(api, message, ctx, path, key)
+        }
+        Err(e) => {
+            let request = message
+                .reply_text(e.explain(name))
+                .allow_sending_without_reply()
+                .with_parse_mode(ParseMode::HTML);
+            api.send_json(&request)?;
+            Ok(())
+        }
+    }
+}
+
+fn reply_item(
+    api: &Api,
+    message: &Message,
+    ctx: &mut Context,
+    path: DocPath,
+    key: fn(DocPath) -> DocKey,
+) -> Result<()> {
+    match resolve(ctx, path) {
+        Resolution::Found(path) => reply_documentation(api, message, ctx, key(path)),
+        Resolution::Ambiguous(candidates) => {
+            let text = format!(
+                "Several items are named <code>{}</code>. Which one do you mean?",
+                ParseMode::HTML.escape(candidates[0].item_name())
+            );
+            let choices = candidates
+                .into_iter()
+                .map(|candidate| (candidate.to_string(), key(candidate)))
+                .collect();
+            reply_choices(api, message, ctx, text, choices)
+        }
+    }
+}
+
+fn reply_book_search(
+    api: &Api,
+    message: &Message,
+    ctx: &mut Context,
+    label: &str,
+    query: &str,
+) -> Result<()> {
+    let book = find_book(label).unwrap();
+    if query.is_empty() {
+        let request = message.reply_text(format!("Usage: {} <query>", label));
+        api.send_json(&request)?;
+        return Ok(());
+    }
+    if !ctx.book_indexes.contains_key(book.title) {
+        match BookIndex::fetch(book) {
+            Ok(Some(index)) => {
+                ctx.book_indexes.insert(book.title, index);
+            }
+            Ok(None) => {}
+            Err(e) => log::error!("cannot fetch search index: {}", e),
+        }
+    }
+
+    let hits = ctx
+        .book_indexes
+        .get(book.title)
+        .map(|index| index.search(query))
+        .unwrap_or_default();
+    if hits.is_empty() {
+        let request = message.reply_text("No sections match that query.");
+        api.send_json(&request)?;
+        return Ok(());
+    }
+    let text = format!(
+        "Sections of <b>{}</b> matching <code>{}</code>:",
+        book.title,
+        ParseMode::HTML.escape(query)
+    );
+    let choices = hits
+        .into_iter()
+        .map(|(title, url)| (title, DocKey::Book(url)))
+        .collect();
+    reply_choices(api, message, ctx, text, choices)
+}
+
+fn parse_mode(ctx: &Context) -> ParseMode {
+    ctx.cached_docs
+        .renderer()
+        .parse_mode()
+        .unwrap_or(ParseMode::HTML)
+}
+
+fn reply_documentation(api: &Api, message: &Message, ctx: &mut Context, key: DocKey) -> Result<()> {
+    let parse_mode = parse_mode(ctx);
+    match ctx.cached_docs.get_or_fetch(&key) {
+        Ok(Some(doc)) if !doc.pages.is_empty() => {
+            let index = doc.start;
+            let page = &doc.pages[index];
+            let mut request = message
+                .reply_text(&page.text)
+                .with_parse_mode(parse_mode)
+                .allow_sending_without_reply()
+                .disable_web_page_preview();
+            if let Some(keyboard) = page.build_keyboard(0) {
+                request = request.with_reply_markup(keyboard);
+            }
+            let message = api.send_json(&request)?;
+            ctx.sessions.insert(
+                message.chat.id,
+                message.message_id,
+                Session { page: index, key },
+            );
+        }
+        Ok(_) => {
+            let request = message.reply_text("Cannot find that item.");
+            api.send_json(&request)?;
+        }
+        Err(e) => log::error!("cannot fetch documentation: {}", e),
+    }
+    Ok(())
+}
+
+fn reply_choices(
+    api: &Api,
+    message: &Message,
+    ctx: &mut Context,
+    text: String,
+    choices: Vec<(String, DocKey)>,
+) -> Result<()> {
+    let mut rows = choices.iter().enumerate().map(|(i, (label, _))| {
+        InlineKeyboardRow::new_emplace(
+            label,
+            InlineKeyboardButtonKind::Callback {
+                callback_data: format!("c{}", i),
+            },
+        )
+    });
+    let keyboard = InlineKeyboardMarkup::new_with_row(rows.next().unwrap());
+    let keyboard = rows.fold(keyboard, InlineKeyboardMarkup::with_row);
+    let request = message
+        .reply_text(text)
+        .with_parse_mode(ParseMode::HTML)
+        .allow_sending_without_reply()
+        .with_reply_markup(keyboard);
+    let message = api.send_json(&request)?;
+    let keys = choices.into_iter().map(|(_, key)| key).collect();
+    ctx.choices
+        .insert(message.chat.id, message.message_id, keys);
+    Ok(())
+}
+
+fn toggle_examples(api: &Api, message: &Message, ctx: &mut Context, show: bool) -> Result<()> {
+    let (from, page) = match ctx.sessions.get_mut(message.chat.id, message.message_id) {
+        Some(session) => (session.key.clone(), session.page),
+        None => return Ok(()),
+    };
+    let key = match (&from, show) {
+        (DocKey::Item(path), true) => DocKey::FullExamples(path.clone()),
+        (DocKey::FullExamples(path), false) => DocKey::Item(path.clone()),
+        _ => return Ok(()),
+    };
+    let example = ctx
+        .cached_docs
+        .get(&from)
+        .and_then(|doc| doc.pages.get(page))
+        .and_then(|page| page.examples.first().copied());
+    let target = match ctx.cached_docs.get_or_fetch(&key) {
+        Ok(Some(doc)) => example.and_then(|example| {
+            doc.pages
+                .iter()
+                .position(|page| page.examples.contains(&example))
+        }),
+        _ => None,
+    };
+    edit_documentation(api, message, ctx, key, target.or(Some(page)))
+}
+
+fn edit_documentation(
+    api: &Api,
+    message: &Message,
+    ctx: &mut Context,
+    key: DocKey,
+    page: Option<usize>,
+) -> Result<()> {
+    let parse_mode = parse_mode(ctx);
+    match ctx.cached_docs.get_or_fetch(&key) {
+        Ok(Some(doc)) if !doc.pages.is_empty() => {
+            let index = page
+                .unwrap_or(doc.start)
+                .min(doc.pages.len().saturating_sub(1));
+            let page = &doc.pages[index];
+            let mut request = EditMessageText::new(message.chat.id, message.message_id, &page.text)
+                .with_parse_mode(parse_mode)
+                .disable_web_page_preview();
+            if let Some(keyboard) = page.build_keyboard(0) {
+                request = request.with_reply_markup(keyboard);
+            }
+            api.send_json(&request)?;
+            ctx.sessions.insert(
+                message.chat.id,
+                message.message_id,
+                Session { page: index, key },
+            );
+        }
+        Ok(_) => {
+            let request = EditMessageText::new(
+                message.chat.id,
+                message.message_id,
+                "Cannot find that item.",
+            );
+            api.send_json(&request)?;
+        }
+        Err(e) => log::error!("cannot fetch documentation: {}", e),
+    }
+    Ok(())
+}
+
+fn find_link(ctx: &mut Context, message: &Message, index: &str) -> Option<DocKey> {
+    let session = ctx.sessions.get_mut(message.chat.id, message.message_id)?;
+    let index = index.parse::<usize>().ok()?;
+    ctx.cached_docs.get(&session.key)?.links.get(index).cloned()
+}
+
+fn send_images(api: &Api, message: &Message, images: &[(String, String)]) -> Result<()> {
+    for chunk in images.chunks(10) {
+        if let [(url, caption)] = chunk {
+            let request = SendPhoto::new(message.chat.id, InputFileVariant::Id(url.clone()))
+                .with_caption(caption);
+            api.send_json(&request)?;
+        } else {
+            let media = chunk
+                .iter()
+                .map(|(url, caption)| {
+                    InputMedia::Photo(
+                        InputMediaPhoto::new(InputFileVariant::Id(url.clone()))
+                            .with_caption(caption),
+                    )
+                })
+                .collect();
+            api.send_json(&SendMediaGroup::new(message.chat.id, media))?;
+        }
+    }
+    Ok(())
+}
+
 fn on_callback(api: &Api, callback_query: &CallbackQuery, ctx: &mut Context) -> Result<()> {
     if let Some(message) = &callback_query.message {
-        if let Some(session) = ctx.sessions.get(message.chat.id, message.message_id) {
+        if let Some(key) = callback_query
+            .data
+            .as_ref()
+            .and_then(|data| data.strip_prefix('c'))
+            .and_then(|data| data.parse::<usize>().ok())
+            .and_then(|index| {
+                ctx.choices
+                    .get(message.chat.id, message.message_id)?
+                    .get(index)
+                    .cloned()
+            })
+        {
+            return edit_documentation(api, message, ctx, key, None);
+        }
+        if let Some(index) = callback_query
+            .data
+            .as_ref()
+            .and_then(|data| data.strip_prefix('k'))
+        {
+            if let Some(key) = find_link(ctx, message, index) {
+                return reply_documentation(api, message, ctx, key);
+            }
+            return Ok(());
+        }
+        if callback_query.data.as_deref() == Some("s") {
+            if let Some(Session {
+                key: DocKey::Item(path) | DocKey::FullExamples(path),
+                ..
+            }) = ctx.sessions.get_mut(message.chat.id, message.message_id)
+            {
+                let key = DocKey::Source(path.clone());
+                return reply_documentation(api, message, ctx, key);
+            }
+            return Ok(());
+        }
+        if let Some(show) = match callback_query.data.as_deref() {
+            Some("e") => Some(true),
+            Some("h") => Some(false),
+            _ => None,
+        } {
+            return toggle_examples(api, message, ctx, show);
+        }
+        if callback_query.data.as_deref() == Some("i") {
+            if let Some(session) = ctx.sessions.get_mut(message.chat.id, message.message_id) {
+                if let Some(page) = ctx
+                    .cached_docs
+                    .get(&session.key)
+                    .and_then(|doc| doc.pages.get(session.page))
+                {
+                    send_images(api, message, &page.images)?;
+                }
+            }
+            return Ok(());
+        }
+        let parse_mode = parse_mode(ctx);
+        if let Some(session) = ctx.sessions.get_mut(message.chat.id, message.message_id) {
             if let Some(index) = callback_query
                 .data
                 .as_ref()
                 .and_then(|data| data.parse::<usize>().ok())
             {
-                if let Some(doc) = ctx.cached_docs.get(&session.path) {
+                if let Some(doc) = ctx.cached_docs.get(&session.key) {
                     if let Some(page) = doc.pages.get(index) {
                         let mut request =
                             EditMessageText::new(message.chat.id, message.message_id, &page.text)
-                                .with_parse_mode(ParseMode::HTML)
+                                .with_parse_mode(parse_mode)
                                 .disable_web_page_preview();
                         if let Some(keyboard) = page.build_keyboard(0) {
                             request = request.with_reply_markup(keyboard);
                         }
                         api.send_json(&request)?;
+                        session.page = index;
                     }
                 }
             } else if let Some(index) = callback_query
@@ -147,7 +611,7 @@ fn on_callback(api: &Api, callback_query: &CallbackQuery, ctx: &mut Context) ->
                 .and_then(|data| data.get(1..))
                 .and_then(|data| data.parse::<usize>().ok())
             {
-                if let Some(doc) = ctx.cached_docs.get(&session.path) {
+                if let Some(doc) = ctx.cached_docs.get(&session.key) {
                     if let Some(page) = doc.pages.get(session.page) {
                         if let Some(keyboard) = page.build_keyboard(index) {
                             let request = EditMessageReplyMarkup::new(
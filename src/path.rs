@@ -1,31 +1,226 @@
+use std::fmt;
+
+use regex::Regex;
+use telbot_ureq::types::markup::ParseMode;
+use url::Url;
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct DocPath {
     crate_name: String,
     modules: Vec<String>,
     item_name: String,
+    kind: Option<ItemKind>,
+    version: Option<String>,
+    fragment: Option<String>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ItemKind {
+    Module,
+    Function,
+    Macro,
+    Attribute,
+    Keyword,
+    Primitive,
+    Struct,
+    Trait,
+    Enum,
+    Derive,
+    Union,
+    Type,
+    Constant,
+    Static,
+}
+
+impl ItemKind {
+    fn from_prefix(prefix: &str) -> Option<Self> {
+        Some(match prefix {
+            "fn" => ItemKind::Function,
+            "macro" => ItemKind::Macro,
+            "attr" => ItemKind::Attribute,
+            "keyword" => ItemKind::Keyword,
+            "primitive" => ItemKind::Primitive,
+            "struct" => ItemKind::Struct,
+            "trait" => ItemKind::Trait,
+            "enum" => ItemKind::Enum,
+            "derive" => ItemKind::Derive,
+            "union" => ItemKind::Union,
+            "type" => ItemKind::Type,
+            "constant" => ItemKind::Constant,
+            "static" => ItemKind::Static,
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
 pub enum DocPathParseError {
     Empty,
-    InvalidCharAt(usize),
+    LeadingSeparator,
+    EmptySegment { segment: usize, at: usize },
+    GenericArguments { segment: usize, at: usize },
+    WhitespaceInSegment { segment: usize, at: usize },
+    InvalidCharAt { segment: usize, at: usize },
+    UnsupportedUrl,
 }
 
+const PRIMITIVES: &[&str] = &[
+    "bool", "char", "f32", "f64", "i8", "i16", "i32", "i64", "i128", "isize", "str", "u8", "u16",
+    "u32", "u64", "u128", "usize",
+];
+
+const KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern",
+    "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub",
+    "ref", "return", "self", "Self", "static", "struct", "super", "trait", "true", "type", "union",
+    "unsafe", "use", "where", "while",
+];
+
 impl DocPath {
+    fn primitive(name: &str) -> Self {
+        Self {
+            crate_name: "std".into(),
+            modules: vec!["std".into()],
+            item_name: name.into(),
+            kind: Some(ItemKind::Primitive),
+            version: None,
+            fragment: None,
+        }
+    }
+
+    fn keyword(name: &str) -> Self {
+        Self {
+            crate_name: "std".into(),
+            modules: vec!["std".into()],
+            item_name: if name == "Self" { "SelfTy" } else { name }.into(),
+            kind: Some(ItemKind::Keyword),
+            version: None,
+            fragment: None,
+        }
+    }
+
+    pub fn crate_root(crate_name: &str) -> Self {
+        Self {
+            crate_name: crate_name.into(),
+            modules: vec![],
+            item_name: crate_name.replace('-', "_"),
+            kind: Some(ItemKind::Module),
+            version: None,
+            fragment: None,
+        }
+    }
+
+    pub fn with_version(mut self, version: &str) -> Self {
+        self.version = Some(version.into());
+        self
+    }
+
+    pub fn with_fragment(mut self, fragment: &str) -> Self {
+        self.fragment = Some(fragment.into());
+        self
+    }
+
+    pub fn from_url(url: &Url) -> Option<Self> {
+        let mut segments = url.path_segments()?.filter(|segment| !segment.is_empty());
+        let (crate_name, version, mut modules) = match url.host_str()? {
+            "docs.rs" => {
+                let mut crate_name = segments.next()?;
+                let is_crate_page = crate_name == "crate";
+                if is_crate_page {
+                    crate_name = segments.next()?;
+                }
+                let version = segments
+                    .next()
+                    .filter(|&version| version != "latest" && version != "*")
+                    .map(String::from);
+                let modules = if is_crate_page {
+                    vec![]
+                } else {
+                    segments.map(String::from).collect()
+                };
+                (crate_name.to_string(), version, modules)
+            }
+            "doc.rust-lang.org" => {
+                let mut crate_name = segments.next()?;
+                let mut version = None;
+                if matches!(crate_name, "stable" | "beta" | "nightly")
+                    || crate_name.starts_with(|c: char| c.is_ascii_digit())
+                {
+                    version = Some(crate_name.to_string());
+                    crate_name = segments.next()?;
+                }
+                if !matches!(crate_name, "alloc" | "core" | "proc_macro" | "std" | "test") {
+                    return None;
+                }
+                let modules = std::iter::once(crate_name)
+                    .chain(segments)
+                    .map(String::from)
+                    .collect();
+                (crate_name.to_string(), version, modules)
+            }
+            _ => return None,
+        };
+        modules.retain(|segment| !segment.contains('-'));
+
+        let mut kind = Some(ItemKind::Module);
+        if modules.last().is_some_and(|file| file.ends_with(".html")) {
+            let file = modules.pop().unwrap();
+            let file = file.trim_end_matches(".html");
+            if let Some((prefix, name)) = file.split_once('.') {
+                kind = ItemKind::from_prefix(prefix);
+                modules.push(name.into());
+            }
+        }
+        if modules.is_empty() {
+            modules.push(crate_name.replace('-', "_"));
+        }
+        let item_name = modules.pop().unwrap();
+        Some(Self {
+            crate_name,
+            modules,
+            item_name,
+            kind,
+            version,
+            fragment: url.fragment().map(String::from),
+        })
+    }
+
+    pub fn fragment(&self) -> Option<&str> {
+        self.fragment.as_deref()
+    }
+
+    pub fn item_name(&self) -> &str {
+        &self.item_name
+    }
+
+    pub fn is_bare(&self) -> bool {
+        self.modules.is_empty() && self.kind.is_none()
+    }
+
     pub fn docs_url(&self) -> Vec<String> {
         let is_std = matches!(
             self.crate_name.as_str(),
             "alloc" | "core" | "proc_macro" | "std" | "test"
         );
         let mut base_url = if is_std {
-            "https://doc.rust-lang.org/".to_string()
+            match &self.version {
+                Some(version) => format!("https://doc.rust-lang.org/{}/", version),
+                None => "https://doc.rust-lang.org/".to_string(),
+            }
         } else {
-            format!("https://docs.rs/{}/*/", self.crate_name)
+            format!(
+                "https://docs.rs/{}/{}/",
+                self.crate_name,
+                self.version.as_deref().unwrap_or("*")
+            )
         };
         for module in &self.modules {
             base_url.push_str(module);
             base_url.push('/');
         }
+        if let Some(kind) = self.kind {
+            return vec![self.kind_url(kind, &base_url)];
+        }
         let mut candidates = vec![];
         if self.item_name.starts_with(char::is_lowercase) {
             candidates.push(self.module_url(&base_url));
@@ -60,6 +255,25 @@ impl DocPath {
         }
     }
 
+    fn kind_url(&self, kind: ItemKind, base_url: &str) -> String {
+        match kind {
+            ItemKind::Module => self.module_url(base_url),
+            ItemKind::Function => self.function_url(base_url),
+            ItemKind::Macro => self.macro_url(base_url),
+            ItemKind::Attribute => self.attribute_url(base_url),
+            ItemKind::Keyword => self.keyword_url(base_url),
+            ItemKind::Primitive => self.primitive_url(base_url),
+            ItemKind::Struct => self.struct_url(base_url),
+            ItemKind::Trait => self.trait_url(base_url),
+            ItemKind::Enum => self.enum_url(base_url),
+            ItemKind::Derive => self.derive_url(base_url),
+            ItemKind::Union => self.union_url(base_url),
+            ItemKind::Type => self.type_url(base_url),
+            ItemKind::Constant => self.constant_url(base_url),
+            ItemKind::Static => self.static_url(base_url),
+        }
+    }
+
     fn module_url(&self, base_url: &str) -> String {
         format!("{}{}", base_url, self.item_name)
     }
@@ -103,33 +317,528 @@ impl DocPath {
     fn union_url(&self, base_url: &str) -> String {
         format!("{}union.{}.html", base_url, self.item_name)
     }
+
+    fn type_url(&self, base_url: &str) -> String {
+        format!("{}type.{}.html", base_url, self.item_name)
+    }
+
+    fn constant_url(&self, base_url: &str) -> String {
+        format!("{}constant.{}.html", base_url, self.item_name)
+    }
+
+    fn static_url(&self, base_url: &str) -> String {
+        format!("{}static.{}.html", base_url, self.item_name)
+    }
+}
+
+impl fmt::Display for DocPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for module in &self.modules {
+            write!(f, "{}::", module)?;
+        }
+        write!(f, "{}", self.item_name)
+    }
 }
 
 impl TryFrom<&str> for DocPath {
     type Error = DocPathParseError;
 
     fn try_from(value: &str) -> Result<Self, Self::Error> {
-        let mut split = value.trim().split("::");
-        let crate_name = split.next().ok_or(DocPathParseError::Empty)?;
-        if let Some(invalid) = crate_name.find(is_not_allowed_path_chat) {
-            return Err(DocPathParseError::InvalidCharAt(invalid));
+        let value = value.trim();
+        if value.is_empty() {
+            return Err(DocPathParseError::Empty);
         }
-        let mut modules = vec![crate_name.into()];
-        for comp in split {
-            if let Some(invalid) = comp.find(is_not_allowed_path_chat) {
-                return Err(DocPathParseError::InvalidCharAt(invalid));
+        if value.starts_with("https://") || value.starts_with("http://") {
+            return Url::parse(value)
+                .ok()
+                .as_ref()
+                .and_then(Self::from_url)
+                .ok_or(DocPathParseError::UnsupportedUrl);
+        }
+        if KEYWORDS.contains(&value) {
+            return Ok(Self::keyword(value));
+        }
+        let (ty, is_reference) = strip_type_prefix(value);
+        if let Some(primitive) = primitive_form(ty, is_reference) {
+            return Ok(Self::primitive(primitive));
+        }
+        let (path, origin) = strip_generics(ty, value.len() - ty.len())?;
+        if path.is_empty() {
+            return Err(DocPathParseError::Empty);
+        }
+        let mut modules = vec![];
+        for (segment, (at, comp)) in segments(&path).enumerate() {
+            if comp.is_empty() {
+                return Err(if segment == 0 {
+                    DocPathParseError::LeadingSeparator
+                } else {
+                    let at = origin[at];
+                    DocPathParseError::EmptySegment { segment, at }
+                });
+            }
+            if let Some((offset, c)) = comp
+                .char_indices()
+                .find(|&(_, c)| is_not_allowed_path_chat(c))
+            {
+                let at = origin[at + offset];
+                return Err(if c.is_whitespace() {
+                    DocPathParseError::WhitespaceInSegment { segment, at }
+                } else {
+                    DocPathParseError::InvalidCharAt { segment, at }
+                });
+            }
+            if segment == 0 {
+                modules.push(comp.into());
+            } else {
+                modules.push(comp.replace('-', "_"));
             }
-            modules.push(comp.replace('-', "_"));
         }
+        let crate_name = modules[0].clone();
         let item_name = modules.pop().unwrap();
+        if modules.is_empty() && PRIMITIVES.contains(&item_name.as_str()) {
+            return Ok(Self::primitive(&item_name));
+        }
         Ok(Self {
-            crate_name: crate_name.into(),
+            crate_name,
             modules,
             item_name,
+            kind: None,
+            version: None,
+            fragment: None,
         })
     }
 }
 
+pub fn find_docs_link(text: &str) -> Option<&str> {
+    Regex::new(r"https?://(docs\.rs|doc\.rust-lang\.org)/\S+")
+        .unwrap()
+        .find(text)
+        .map(|link| {
+            let mut link = link.as_str();
+            loop {
+                let trimmed = link.trim_end_matches(['.', ',', ';', ':', '!', '?', '\'', '"', '>']);
+                link = match trimmed.strip_suffix(')') {
+                    Some(rest) if trimmed.matches('(').count() < trimmed.matches(')').count() => {
+                        rest
+                    }
+                    _ => return trimmed,
+                };
+            }
+        })
+}
+
+pub fn attribute_name(input: &str) -> Option<&str> {
+    let inner = input
+        .trim()
+        .strip_prefix('#')?
+        .trim_start_matches('!')
+        .strip_prefix('[')?
+        .strip_suffix(']')?
+        .trim();
+    let name = inner
+        .split(|c: char| c == '(' || c == '=' || c.is_whitespace())
+        .next()?;
+    if !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+        Some(name)
+    } else {
+        None
+    }
+}
+
+fn strip_type_prefix(mut ty: &str) -> (&str, bool) {
+    let mut is_reference = false;
+    loop {
+        let trimmed = ty.trim_start();
+        if let Some(rest) = trimmed.strip_prefix('&') {
+            is_reference = true;
+            ty = rest;
+        } else if let Some(rest) = trimmed.strip_prefix('\'') {
+            ty = rest.trim_start_matches(|c: char| c.is_alphanumeric() || c == '_');
+        } else if let Some(rest) = ["mut ", "dyn ", "impl "]
+            .iter()
+            .find_map(|keyword| trimmed.strip_prefix(keyword))
+        {
+            ty = rest;
+        } else {
+            return (trimmed, is_reference);
+        }
+    }
+}
+
+fn primitive_form(ty: &str, is_reference: bool) -> Option<&'static str> {
+    let ty = ty.trim_end();
+    if let Some(inner) = ty.strip_prefix('[') {
+        let mut depth = 0;
+        for c in inner.chars() {
+            match c {
+                '[' | '(' | '<' => depth += 1,
+                ']' | ')' | '>' => depth -= 1,
+                ';' if depth == 0 => return Some("array"),
+                _ => {}
+            }
+        }
+        Some("slice")
+    } else if let Some(inner) = ty.strip_prefix('(') {
+        if inner.trim_start().starts_with(')') {
+            Some("unit")
+        } else {
+            Some("tuple")
+        }
+    } else if ty == "!" {
+        Some("never")
+    } else if ty.starts_with("*const") || ty.starts_with("*mut") {
+        Some("pointer")
+    } else if Regex::new(r#"^(unsafe\s+)?(extern\s+("[^"]*"\s*)?)?fn\s*\("#)
+        .unwrap()
+        .is_match(ty)
+    {
+        Some("fn")
+    } else if let Some(&primitive) = PRIMITIVES.iter().find(|&&primitive| primitive == ty) {
+        Some(primitive)
+    } else if is_reference && (ty.is_empty() || ty.len() == 1 && ty.starts_with(char::is_uppercase))
+    {
+        Some("reference")
+    } else {
+        None
+    }
+}
+
+fn strip_generics(ty: &str, offset: usize) -> Result<(String, Vec<usize>), DocPathParseError> {
+    let mut path = String::new();
+    let mut origin = vec![];
+    let mut open: Vec<(char, usize)> = vec![];
+    let mut chars = ty.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let at = offset + i;
+        let segment = ty[..i].matches("::").count();
+        match c {
+            '<' | '(' => {
+                if open.is_empty() && path.ends_with("::") {
+                    path.truncate(path.len() - 2);
+                    origin.truncate(origin.len() - 2);
+                }
+                open.push((c, at));
+            }
+            '-' if open.is_empty() && matches!(chars.peek(), Some((_, '>'))) => break,
+            '+' if open.is_empty() => break,
+            '>' | ')' => match open.pop() {
+                Some(('<', _)) if c == '>' => {}
+                Some(('(', _)) if c == ')' => {}
+                Some((_, at)) => return Err(DocPathParseError::GenericArguments { segment, at }),
+                None => return Err(DocPathParseError::InvalidCharAt { segment, at }),
+            },
+            _ if open.is_empty() => {
+                path.push(c);
+                origin.extend(std::iter::repeat(at).take(c.len_utf8()));
+            }
+            _ => {}
+        }
+    }
+    if let Some(&(_, at)) = open.first() {
+        let segment = ty[..at - offset].matches("::").count();
+        return Err(DocPathParseError::GenericArguments { segment, at });
+    }
+    let end = path.trim_end().len();
+    path.truncate(end);
+    origin.truncate(end);
+    origin.push(origin.last().map_or(offset, |&at| at + 1));
+    Ok((path, origin))
+}
+
+fn segments(value: &str) -> impl Iterator<Item = (usize, &str)> {
+    let mut at = 0;
+    value.split("::").map(move |comp| {
+        let begin = at;
+        at += comp.len() + 2;
+        (begin, comp)
+    })
+}
+
 fn is_not_allowed_path_chat(c: char) -> bool {
     !(c.is_ascii_alphanumeric() || c == '_' || c == '-')
 }
+
+impl DocPathParseError {
+    pub fn explain(&self, input: &str) -> String {
+        let input = input.trim();
+        let (at, segment) = match *self {
+            DocPathParseError::Empty => return "Usage: /docs &lt;item path&gt;".into(),
+            DocPathParseError::UnsupportedUrl => {
+                return concat!(
+                    "Only item pages on <code>docs.rs</code> and ",
+                    "<code>doc.rust-lang.org</code> can be looked up."
+                )
+                .into()
+            }
+            DocPathParseError::LeadingSeparator => (0, 0),
+            DocPathParseError::EmptySegment { segment, at }
+            | DocPathParseError::GenericArguments { segment, at }
+            | DocPathParseError::WhitespaceInSegment { segment, at }
+            | DocPathParseError::InvalidCharAt { segment, at } => (at, segment),
+        };
+        let column = input[..at].chars().count();
+        let mut text = format!(
+            "<pre>{}\n{}^</pre>\n",
+            ParseMode::HTML.escape(input),
+            " ".repeat(column)
+        );
+        match self {
+            DocPathParseError::Empty | DocPathParseError::UnsupportedUrl => unreachable!(),
+            DocPathParseError::LeadingSeparator => {
+                text.push_str("The path starts with <code>::</code>. ");
+                text.push_str(
+                    "Begin with the crate name instead, e.g. <code>std::vec::Vec</code>.",
+                );
+            }
+            DocPathParseError::EmptySegment { .. } => {
+                text.push_str(&format!("Segment {} is empty. ", segment + 1));
+                text.push_str("Separate each segment with exactly one <code>::</code> ");
+                text.push_str("and do not end the path with <code>::</code>.");
+            }
+            DocPathParseError::GenericArguments { .. } => {
+                text.push_str(&format!(
+                    "Segment {} (<code>{}</code>) has unbalanced brackets. ",
+                    segment + 1,
+                    ParseMode::HTML.escape(segment_at(input, segment))
+                ));
+                text.push_str("Close every <code>&lt;</code> with <code>&gt;</code> ");
+                text.push_str("and every <code>(</code> with <code>)</code>.");
+            }
+            DocPathParseError::WhitespaceInSegment { .. } => {
+                text.push_str(&format!(
+                    "Segment {} (<code>{}</code>) contains whitespace. ",
+                    segment + 1,
+                    ParseMode::HTML.escape(segment_at(input, segment))
+                ));
+                text.push_str("Remove the whitespace from that segment.");
+            }
+            DocPathParseError::InvalidCharAt { .. } => {
+                let c = input[at..].chars().next().unwrap();
+                text.push_str(&format!(
+                    "Segment {} (<code>{}</code>) contains <code>{}</code>. ",
+                    segment + 1,
+                    ParseMode::HTML.escape(segment_at(input, segment)),
+                    ParseMode::HTML.escape(c.to_string())
+                ));
+                if c == ':' {
+                    text.push_str("Separate segments with <code>::</code>, not <code>:</code>.");
+                } else {
+                    text.push_str("Segments may only contain alphanumerics, ");
+                    text.push_str("underscore (<code>_</code>), or hyphen (<code>-</code>).");
+                }
+            }
+        }
+        text.push_str("\n\n<b>Item Path Format</b>\n");
+        text.push_str("<code>&lt;crate name&gt;::&lt;module1&gt;::…::&lt;item name&gt;</code>");
+        text.push_str("\nKeywords (<code>async</code>) and attributes (<code>#[derive]</code>) are accepted too.");
+        text
+    }
+}
+
+fn segment_at(input: &str, segment: usize) -> &str {
+    input.split("::").nth(segment).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_type_prefixes() {
+        for (input, expected) in [
+            ("HashMap<K, V>", ("HashMap<K, V>", false)),
+            ("&'a mut [T]", ("[T]", true)),
+            ("&str", ("str", true)),
+            ("dyn Fn() + Send", ("Fn() + Send", false)),
+            ("impl Iterator<Item = u8>", ("Iterator<Item = u8>", false)),
+        ] {
+            assert_eq!(strip_type_prefix(input), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn finds_primitive_forms() {
+        for (input, is_reference, expected) in [
+            ("[T]", true, Some("slice")),
+            ("[u8; 4]", false, Some("array")),
+            ("[[u8; 4]]", false, Some("slice")),
+            ("()", false, Some("unit")),
+            ("(u8, u16)", false, Some("tuple")),
+            ("fn(u8) -> u8", false, Some("fn")),
+            ("unsafe extern \"C\" fn()", false, Some("fn")),
+            ("*const u8", false, Some("pointer")),
+            ("!", false, Some("never")),
+            ("u8", false, Some("u8")),
+            ("T", true, Some("reference")),
+            ("HashMap<K, V>", false, None),
+            ("Fn() + Send", false, None),
+        ] {
+            assert_eq!(primitive_form(input, is_reference), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn strips_generics() {
+        for (input, offset, path, origin) in [
+            ("HashMap<K, V>", 0, "HashMap", vec![0, 1, 2, 3, 4, 5, 6, 7]),
+            (
+                "Vec::<T>::new",
+                0,
+                "Vec::new",
+                vec![0, 1, 2, 8, 9, 10, 11, 12, 13],
+            ),
+            ("Fn() + Send", 4, "Fn", vec![4, 5, 6]),
+            (
+                "Iterator<Item = u8>",
+                0,
+                "Iterator",
+                vec![0, 1, 2, 3, 4, 5, 6, 7, 8],
+            ),
+        ] {
+            assert_eq!(
+                strip_generics(input, offset).unwrap(),
+                (path.to_string(), origin),
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn parses_urls() {
+        for (url, expected, fragment) in [
+            (
+                "https://docs.rs/serde/1.0.0/serde/de/trait.Deserialize.html",
+                "https://docs.rs/serde/1.0.0/serde/de/trait.Deserialize.html",
+                None,
+            ),
+            (
+                "https://docs.rs/serde/latest/serde/",
+                "https://docs.rs/serde/*/serde",
+                None,
+            ),
+            (
+                "https://docs.rs/crate/tokio/1.0.0",
+                "https://docs.rs/tokio/1.0.0/tokio",
+                None,
+            ),
+            (
+                "https://doc.rust-lang.org/std/vec/struct.Vec.html#method.push",
+                "https://doc.rust-lang.org/std/vec/struct.Vec.html",
+                Some("method.push"),
+            ),
+            (
+                "https://doc.rust-lang.org/stable/std/primitive.u8.html#method.pow",
+                "https://doc.rust-lang.org/stable/std/primitive.u8.html",
+                Some("method.pow"),
+            ),
+            (
+                "https://doc.rust-lang.org/1.70.0/core/option/enum.Option.html#method.map",
+                "https://doc.rust-lang.org/1.70.0/core/option/enum.Option.html",
+                Some("method.map"),
+            ),
+        ] {
+            let path = DocPath::from_url(&Url::parse(url).unwrap()).unwrap();
+            assert_eq!(path.docs_url(), [expected], "{}", url);
+            assert_eq!(path.fragment(), fragment, "{}", url);
+        }
+        for url in [
+            "https://doc.rust-lang.org/book/ch01-00-getting-started.html",
+            "https://example.com/std/vec/struct.Vec.html",
+        ] {
+            assert_eq!(
+                DocPath::from_url(&Url::parse(url).unwrap()),
+                None,
+                "{}",
+                url
+            );
+        }
+    }
+
+    #[test]
+    fn finds_docs_links() {
+        for (text, expected) in [
+            (
+                "see https://docs.rs/serde/latest/serde/de/trait.Deserialize.html.",
+                Some("https://docs.rs/serde/latest/serde/de/trait.Deserialize.html"),
+            ),
+            (
+                "(https://doc.rust-lang.org/std/vec/struct.Vec.html#method.push), then",
+                Some("https://doc.rust-lang.org/std/vec/struct.Vec.html#method.push"),
+            ),
+            (
+                "https://doc.rust-lang.org/std/primitive.fn.html#impl-Fn(A)",
+                Some("https://doc.rust-lang.org/std/primitive.fn.html#impl-Fn(A)"),
+            ),
+            ("https://example.com/std/vec/struct.Vec.html", None),
+        ] {
+            assert_eq!(find_docs_link(text), expected, "{}", text);
+        }
+    }
+
+    #[test]
+    fn finds_attribute_names() {
+        for (input, expected) in [
+            ("#[derive(Debug)]", Some("derive")),
+            ("#![allow(unused)]", Some("allow")),
+            ("#[doc = \"text\"]", Some("doc")),
+            ("#[ cfg ]", Some("cfg")),
+            ("#[serde::rename]", None),
+            ("#[]", None),
+            ("derive", None),
+        ] {
+            assert_eq!(attribute_name(input), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn reports_error_offsets() {
+        for (input, error, caret) in [
+            ("", DocPathParseError::Empty, None),
+            (
+                "https://example.com/",
+                DocPathParseError::UnsupportedUrl,
+                None,
+            ),
+            ("::std", DocPathParseError::LeadingSeparator, Some(0)),
+            (
+                "std::::Vec",
+                DocPathParseError::EmptySegment { segment: 1, at: 5 },
+                Some(5),
+            ),
+            (
+                "std::vec::Vec<T",
+                DocPathParseError::GenericArguments { segment: 2, at: 13 },
+                Some(13),
+            ),
+            (
+                "&mut foo::bar<T",
+                DocPathParseError::GenericArguments { segment: 1, at: 13 },
+                Some(13),
+            ),
+            (
+                "std:: vec",
+                DocPathParseError::WhitespaceInSegment { segment: 1, at: 5 },
+                Some(5),
+            ),
+            (
+                "std:vec",
+                DocPathParseError::InvalidCharAt { segment: 0, at: 3 },
+                Some(3),
+            ),
+            (
+                "Vec>",
+                DocPathParseError::InvalidCharAt { segment: 0, at: 3 },
+                Some(3),
+            ),
+        ] {
+            let actual = DocPath::try_from(input).unwrap_err();
+            assert_eq!(actual, error, "{}", input);
+            let explanation = actual.explain(input);
+            if let Some(caret) = caret {
+                let pointer = format!("\n{}^</pre>", " ".repeat(caret));
+                assert!(explanation.contains(&pointer), "{}", input);
+            }
+        }
+    }
+}
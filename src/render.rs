@@ -0,0 +1,167 @@
+use std::env;
+
+use telbot_ureq::types::markup::ParseMode;
+
+pub enum Markup<'a> {
+    Link(&'a str),
+    Bold,
+    Italic,
+    Underline,
+    Strikethrough,
+}
+
+pub enum Block {
+    InlineCode,
+    RustCode,
+    Preformatted,
+}
+
+pub trait Renderer {
+    fn parse_mode(&self) -> Option<ParseMode>;
+    fn escape(&self, text: &str, in_code: bool) -> String;
+    fn markup(&self, markup: Markup) -> (String, String);
+    fn block(&self, block: Block) -> (&'static str, &'static str);
+}
+
+pub struct HtmlRenderer;
+
+impl Renderer for HtmlRenderer {
+    fn parse_mode(&self) -> Option<ParseMode> {
+        Some(ParseMode::HTML)
+    }
+
+    fn escape(&self, text: &str, _: bool) -> String {
+        ParseMode::HTML.escape(text)
+    }
+
+    fn markup(&self, markup: Markup) -> (String, String) {
+        let (open, close) = match markup {
+            Markup::Link(href) => {
+                let open = format!("<a href=\"{}\">", href.replace('"', "\\\""));
+                return (open, "</a>".into());
+            }
+            Markup::Bold => ("<b>", "</b>"),
+            Markup::Italic => ("<i>", "</i>"),
+            Markup::Underline => ("<u>", "</u>"),
+            Markup::Strikethrough => ("<s>", "</s>"),
+        };
+        (open.into(), close.into())
+    }
+
+    fn block(&self, block: Block) -> (&'static str, &'static str) {
+        match block {
+            Block::InlineCode => ("<code>", "</code>"),
+            Block::RustCode => ("<pre><code class=\"language-rust\">", "</code></pre>"),
+            Block::Preformatted => ("<pre>", "</pre>"),
+        }
+    }
+}
+
+pub struct MarkdownRenderer;
+
+impl Renderer for MarkdownRenderer {
+    fn parse_mode(&self) -> Option<ParseMode> {
+        Some(ParseMode::MarkdownV2)
+    }
+
+    fn escape(&self, text: &str, in_code: bool) -> String {
+        let special: &[char] = if in_code {
+            &['`', '\\']
+        } else {
+            &[
+                '_', '*', '[', ']', '(', ')', '~', '`', '>', '#', '+', '-', '=', '|', '{', '}',
+                '.', '!', '\\',
+            ]
+        };
+        let mut escaped = String::with_capacity(text.len());
+        for c in text.chars() {
+            if special.contains(&c) {
+                escaped.push('\\');
+            }
+            escaped.push(c);
+        }
+        escaped
+    }
+
+    fn markup(&self, markup: Markup) -> (String, String) {
+        let (open, close) = match markup {
+            Markup::Link(href) => {
+                let href = href.replace('\\', "\\\\").replace(')', "\\)");
+                return ("[".into(), format!("]({})", href));
+            }
+            Markup::Bold => ("*", "*"),
+            Markup::Italic => ("_", "_"),
+            Markup::Underline => ("__", "__"),
+            Markup::Strikethrough => ("~", "~"),
+        };
+        (open.into(), close.into())
+    }
+
+    fn block(&self, block: Block) -> (&'static str, &'static str) {
+        match block {
+            Block::InlineCode => ("`", "`"),
+            Block::RustCode => ("```rust\n", "\n```"),
+            Block::Preformatted => ("```\n", "\n```"),
+        }
+    }
+}
+
+pub struct AnsiRenderer;
+
+impl Renderer for AnsiRenderer {
+    fn parse_mode(&self) -> Option<ParseMode> {
+        None
+    }
+
+    fn escape(&self, text: &str, _: bool) -> String {
+        text.replace('\x1b', "")
+    }
+
+    fn markup(&self, markup: Markup) -> (String, String) {
+        let (open, close) = match markup {
+            Markup::Link(href) => {
+                let open = format!("\x1b]8;;{}\x1b\\\x1b[4m", href.replace('\x1b', ""));
+                return (open, "\x1b[24m\x1b]8;;\x1b\\".into());
+            }
+            Markup::Bold => ("\x1b[1m", "\x1b[22m"),
+            Markup::Italic => ("\x1b[3m", "\x1b[23m"),
+            Markup::Underline => ("\x1b[4m", "\x1b[24m"),
+            Markup::Strikethrough => ("\x1b[9m", "\x1b[29m"),
+        };
+        (open.into(), close.into())
+    }
+
+    fn block(&self, block: Block) -> (&'static str, &'static str) {
+        match block {
+            Block::InlineCode | Block::Preformatted => ("\x1b[36m", "\x1b[39m"),
+            Block::RustCode => ("\x1b[33m", "\x1b[39m"),
+        }
+    }
+}
+
+pub struct PlainRenderer;
+
+impl Renderer for PlainRenderer {
+    fn parse_mode(&self) -> Option<ParseMode> {
+        None
+    }
+
+    fn escape(&self, text: &str, _: bool) -> String {
+        text.into()
+    }
+
+    fn markup(&self, _: Markup) -> (String, String) {
+        (String::new(), String::new())
+    }
+
+    fn block(&self, _: Block) -> (&'static str, &'static str) {
+        ("", "")
+    }
+}
+
+pub fn renderer() -> &'static dyn Renderer {
+    match env::var("PARSE_MODE").as_deref() {
+        Ok("MarkdownV2") | Ok("markdown") => &MarkdownRenderer,
+        _ => &HtmlRenderer,
+    }
+}
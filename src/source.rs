@@ -0,0 +1,84 @@
+use paradocs::{Paragraph, TextPart};
+use regex::Regex;
+
+use crate::{
+    docs::{
+        build_pages, fetch_html, fetch_page, find_source_link, linked_title, strip_tags,
+        Documentation,
+    },
+    path::DocPath,
+    render::Renderer,
+};
+
+pub fn fetch_source(
+    path: &DocPath,
+    renderer: &dyn Renderer,
+) -> Result<Option<Documentation>, ureq::Error> {
+    let (url, html) = match fetch_html(path)? {
+        Some(page) => page,
+        None => return Ok(None),
+    };
+    let source_url =
+        match find_source_link(&html, path.fragment()).and_then(|href| url.join(&href).ok()) {
+            Some(source_url) => source_url,
+            None => return Ok(None),
+        };
+    let (_, source) = match fetch_page(source_url.as_str())? {
+        Some(page) => page,
+        None => return Ok(None),
+    };
+    let lines = source_lines(&source);
+    if lines.is_empty() {
+        return Ok(None);
+    }
+    let (begin, end) = source_url
+        .fragment()
+        .and_then(line_range)
+        .map_or((1, lines.len()), |(begin, end)| {
+            (begin.max(1), end.min(lines.len()))
+        });
+    if begin > end {
+        return Ok(None);
+    }
+
+    let width = end.to_string().len();
+    let code = lines
+        .iter()
+        .enumerate()
+        .take(end)
+        .skip(begin - 1)
+        .map(|(number, line)| format!("{:>width$} {}", number + 1, line, width = width))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let title = format!("Source of {}", path);
+    let paragraphs = [Paragraph::Code(vec![TextPart::Text(&code)])];
+    Ok(Some(build_pages(renderer, |writer| {
+        writer.write_paragraphs(&linked_title(&title, &source_url), &paragraphs, &source_url)
+    })))
+}
+
+fn line_range(fragment: &str) -> Option<(usize, usize)> {
+    let fragment = fragment.replace('L', "");
+    match fragment.split_once('-') {
+        Some((begin, end)) => Some((begin.parse().ok()?, end.parse().ok()?)),
+        None => {
+            let line = fragment.parse().ok()?;
+            Some((line, line))
+        }
+    }
+}
+
+fn source_lines(html: &str) -> Vec<String> {
+    let code = match Regex::new(r#"(?s)<pre class="?rust"?[^>]*>(.*?)</pre>"#)
+        .unwrap()
+        .captures(html)
+    {
+        Some(captures) => captures[1].to_string(),
+        None => return vec![],
+    };
+    let code = Regex::new(r#"<a [^>]*data-nosnippet[^>]*>\d+</a>"#)
+        .unwrap()
+        .replace_all(&code, "");
+    strip_tags(&code).lines().map(String::from).collect()
+}